@@ -0,0 +1,118 @@
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::spi::SpiDevice;
+use simple_network::{
+    AsyncSimpleNetwork, EtherType, EthernetHeader, FilterMode, MacAddress, ReceiveError,
+    TransmitError,
+};
+
+use super::{receive_error, receive_filter, transmit_error};
+use crate::{Enc28j60Async, Ready};
+
+impl<SPI, INT, RST> AsyncSimpleNetwork for Enc28j60Async<SPI, INT, RST, Ready>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    /// A frame too large for `buf` is left pending when [`ReceiveError::BufferTooSmall`] is
+    /// returned, so it can be received again into a buffer of the reported size.
+    async fn receive(&mut self, buf: &mut [u8]) -> Result<usize, ReceiveError> {
+        let Some((header, packet)) = self.peek_packet(buf).await.map_err(receive_error)? else {
+            return Ok(0);
+        };
+        if packet.is_truncated() {
+            return Err(ReceiveError::BufferTooSmall(packet.total));
+        }
+        self.release_packet(&header).await.map_err(receive_error)?;
+        Ok(packet.copied)
+    }
+
+    async fn transmit(
+        &mut self,
+        dst: &MacAddress,
+        src: &MacAddress,
+        ether_type: EtherType,
+        data: &[u8],
+    ) -> Result<(), TransmitError> {
+        self.transmit(&dst.octets(), &src.octets(), ether_type.as_u16(), data)
+            .await
+            .map(|_| ())
+            .map_err(transmit_error)
+    }
+
+    async fn link_up(&mut self) -> Result<bool, ReceiveError> {
+        self.link_up().await.map_err(receive_error)
+    }
+
+    async fn mac_address(&mut self) -> Result<MacAddress, ReceiveError> {
+        self.read_mac_address()
+            .await
+            .map(MacAddress::from)
+            .map_err(receive_error)
+    }
+
+    fn mtu(&self) -> usize {
+        // MAMXFL counts the header and the CRC as well.
+        usize::from(self.max_frame_length()) - EthernetHeader::LEN - 4
+    }
+
+    async fn set_receive_filter(&mut self, mode: FilterMode) -> Result<(), ReceiveError> {
+        self.set_receive_filter(receive_filter(mode))
+            .await
+            .map_err(receive_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use simple_network::{AsyncSimpleNetwork, ReceiveError};
+
+    use crate::mock::{async_ready_driver, block_on};
+    use crate::register::{ECON2, Econ2, Op};
+
+    #[test]
+    fn receive_keeps_frame_too_large_for_buffer() {
+        let (mut enc, spi) = async_ready_driver();
+        let frame: Vec<u8> = (0..1500).map(|i| i as u8).collect();
+        let script = || {
+            // EPKTCNT, EIR
+            spi.respond(&[0, 1, 0, 0, 0, 0]);
+            // Receive status vector: next packet at 0x0600, 1500 bytes and the CRC, received OK
+            spi.respond(&[0x00, 0x06, 0xe0, 0x05, 0x80, 0x00]);
+        };
+
+        script();
+        spi.respond(&frame[..64]);
+        let result = block_on(AsyncSimpleNetwork::receive(&mut enc, &mut [0u8; 64]));
+        assert!(matches!(result, Err(ReceiveError::BufferTooSmall(1500))));
+        // The frame was not released.
+        let pktdec = vec![ECON2.opcode(Op::BFS), Econ2::PKTDEC.bits()];
+        assert!(!spi.take_written().contains(&pktdec));
+
+        script();
+        spi.respond(&frame);
+        let mut buf = [0u8; 1518];
+        let len = block_on(AsyncSimpleNetwork::receive(&mut enc, &mut buf)).unwrap();
+        assert_eq!(len, 1500);
+        assert_eq!(buf[..1500], frame);
+        let written = spi.take_written();
+        assert!(written.contains(&pktdec));
+        // The frame is released without reading its header again.
+        let rbm = vec![(Op::RBM as u8) | 0x1a];
+        assert_eq!(written.iter().filter(|w| **w == rbm).count(), 2);
+    }
+
+    #[test]
+    fn receive_without_pending_packet_returns_zero() {
+        let (mut enc, _) = async_ready_driver();
+        // The mock reads back EPKTCNT as zero.
+        assert_eq!(block_on(enc.receive(&mut [0u8; 64])), Ok(None));
+        let len = block_on(AsyncSimpleNetwork::receive(&mut enc, &mut [0u8; 64]));
+        assert_eq!(len.unwrap(), 0);
+    }
+}
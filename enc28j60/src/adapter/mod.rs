@@ -1 +1,76 @@
+#[cfg(feature = "async")]
+mod async_simple_network;
 mod simple_network;
+
+use ::simple_network::{FilterMode, ReceiveError, TransmitError};
+
+use crate::{Error, ReceiveFilter};
+
+/// Maps a driver error to the closest `SimpleNetwork` receive error.
+fn receive_error<E>(error: Error<E>) -> ReceiveError {
+    match error {
+        Error::Timeout => ReceiveError::Timeout,
+        Error::NotInitialized => ReceiveError::NotInitialized,
+        Error::BufferTooSmall(len) => ReceiveError::BufferTooSmall(len),
+        Error::Aborted
+        | Error::LateCollision
+        | Error::ExcessiveCollisions
+        | Error::FrameTooLong
+        | Error::Pin
+        | Error::CorruptReceiveBuffer
+        | Error::RuntFrame
+        | Error::DeviceNotResponding
+        | Error::UnsupportedRevision(_)
+        | Error::InvalidArgument
+        | Error::RequiresFullDuplex
+        | Error::Busy
+        | Error::Spi(_) => ReceiveError::DeviceError,
+    }
+}
+
+/// Maps a driver error to the closest `SimpleNetwork` transmit error.
+fn transmit_error<E>(error: Error<E>) -> TransmitError {
+    match error {
+        Error::Timeout | Error::Busy => TransmitError::Timeout,
+        Error::Aborted | Error::LateCollision | Error::ExcessiveCollisions => {
+            TransmitError::Aborted
+        }
+        Error::NotInitialized => TransmitError::NotInitialized,
+        Error::FrameTooLong | Error::BufferTooSmall(_) | Error::InvalidArgument => {
+            TransmitError::InvalidParameter
+        }
+        Error::Pin
+        | Error::CorruptReceiveBuffer
+        | Error::RuntFrame
+        | Error::DeviceNotResponding
+        | Error::UnsupportedRevision(_)
+        | Error::RequiresFullDuplex
+        | Error::Spi(_) => TransmitError::DeviceError,
+    }
+}
+
+/// Translates a `SimpleNetwork` filter mode into the receive filters which implement it.
+fn receive_filter(mode: FilterMode) -> ReceiveFilter {
+    if mode.contains(FilterMode::PROMISCUOUS) {
+        // With no filter enabled, every frame is accepted, even with an invalid CRC.
+        return ReceiveFilter::empty();
+    }
+
+    let mut filter = ReceiveFilter::CRC_CHECK;
+    if mode.contains(FilterMode::UNICAST) {
+        filter |= ReceiveFilter::UNICAST;
+    }
+    if mode.contains(FilterMode::BROADCAST) {
+        filter |= ReceiveFilter::BROADCAST;
+    }
+    if mode.contains(FilterMode::MULTICAST) {
+        filter |= ReceiveFilter::MULTICAST;
+    }
+
+    if filter == ReceiveFilter::CRC_CHECK {
+        // The device accepts every frame when no filter is enabled. Requiring frames to be both
+        // unicast to us and broadcast instead rejects them all.
+        filter |= ReceiveFilter::UNICAST | ReceiveFilter::BROADCAST | ReceiveFilter::AND;
+    }
+    filter
+}
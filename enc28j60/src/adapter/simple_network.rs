@@ -1,17 +1,29 @@
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal::spi::SpiDevice;
-use simple_network::{EtherType, MacAddress, ReceiveError, SimpleNetwork, TransmitError};
+use simple_network::{
+    EtherType, EthernetHeader, FilterMode, MacAddress, ReceiveError, SimpleNetwork, TransmitError,
+};
 
-use crate::Enc28j60;
+use super::{receive_error, receive_filter, transmit_error};
+use crate::{Enc28j60, Ready};
 
-impl<SPI, INT, RST> SimpleNetwork for Enc28j60<SPI, INT, RST>
+impl<SPI, INT, RST, DELAY> SimpleNetwork for Enc28j60<SPI, INT, RST, Ready, DELAY>
 where
     SPI: SpiDevice,
     INT: InputPin,
     RST: OutputPin,
 {
+    /// A frame too large for `buf` is left pending when [`ReceiveError::BufferTooSmall`] is
+    /// returned, so it can be received again into a buffer of the reported size.
     fn receive(&mut self, buf: &mut [u8]) -> Result<usize, ReceiveError> {
-        self.receive(buf).map_err(|_| ReceiveError::DeviceError)
+        let Some((header, packet)) = self.peek_packet(buf).map_err(receive_error)? else {
+            return Ok(0);
+        };
+        if packet.is_truncated() {
+            return Err(ReceiveError::BufferTooSmall(packet.total));
+        }
+        self.release_packet(&header).map_err(receive_error)?;
+        Ok(packet.copied)
     }
 
     fn transmit(
@@ -22,6 +34,128 @@ where
         data: &[u8],
     ) -> Result<(), TransmitError> {
         self.transmit(&dst.octets(), &src.octets(), ether_type.as_u16(), data)
-            .map_err(|_| TransmitError::DeviceError)
+            .map(|_| ())
+            .map_err(transmit_error)
+    }
+
+    fn link_up(&mut self) -> Result<bool, ReceiveError> {
+        self.link_up().map_err(receive_error)
+    }
+
+    fn mac_address(&mut self) -> Result<MacAddress, ReceiveError> {
+        self.read_mac_address()
+            .map(MacAddress::from)
+            .map_err(receive_error)
+    }
+
+    fn mtu(&self) -> usize {
+        // MAMXFL counts the header and the CRC as well.
+        usize::from(self.max_frame_length()) - EthernetHeader::LEN - 4
+    }
+
+    fn set_receive_filter(&mut self, mode: FilterMode) -> Result<(), ReceiveError> {
+        self.set_receive_filter(receive_filter(mode))
+            .map_err(receive_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use simple_network::{FilterMode, ReceiveError, SimpleNetwork};
+
+    use crate::mock::{EREVID_B7, NoDelay, driver, ready_driver};
+    use crate::register::{ECON2, Econ2, Op};
+    use crate::{DEFAULT_MAC_ADDRESS, Duplex};
+
+    #[test]
+    fn link_up_reads_phstat2() {
+        let (mut enc, spi) = ready_driver();
+        // MISTAT not busy, MIRDL, MIRDH with PHSTAT2.LSTAT set
+        spi.respond(&[0, 0, 0, 0, 0, 0, 0, 0, 0x04]);
+        assert_eq!(SimpleNetwork::link_up(&mut enc).ok(), Some(true));
+    }
+
+    #[test]
+    fn mac_address_reads_maadr() {
+        let (mut enc, spi) = ready_driver();
+        // MAADR1 to MAADR6, in wire order
+        for octet in [0x02, 0x00, 0x00, 0x12, 0x34, 0x56] {
+            spi.respond(&[0, 0, octet]);
+        }
+        let mac = SimpleNetwork::mac_address(&mut enc).unwrap();
+        assert_eq!(mac.octets(), [0x02, 0x00, 0x00, 0x12, 0x34, 0x56]);
+    }
+
+    #[test]
+    fn receive_keeps_frame_too_large_for_buffer() {
+        let (mut enc, spi) = ready_driver();
+        let frame: Vec<u8> = (0..1500).map(|i| i as u8).collect();
+        let script = || {
+            // EPKTCNT, EIR
+            spi.respond(&[0, 1, 0, 0, 0, 0]);
+            // Receive status vector: next packet at 0x0600, 1500 bytes and the CRC, received OK
+            spi.respond(&[0x00, 0x06, 0xe0, 0x05, 0x80, 0x00]);
+        };
+
+        script();
+        spi.respond(&frame[..64]);
+        let result = SimpleNetwork::receive(&mut enc, &mut [0u8; 64]);
+        assert!(matches!(result, Err(ReceiveError::BufferTooSmall(1500))));
+        // The frame was not released.
+        let pktdec = vec![ECON2.opcode(Op::BFS), Econ2::PKTDEC.bits()];
+        assert!(!spi.take_written().contains(&pktdec));
+
+        script();
+        spi.respond(&frame);
+        let mut buf = [0u8; 1518];
+        assert_eq!(SimpleNetwork::receive(&mut enc, &mut buf).unwrap(), 1500);
+        assert_eq!(buf[..1500], frame);
+        let written = spi.take_written();
+        assert!(written.contains(&pktdec));
+        // The frame is released without reading its header again.
+        let rbm = vec![(Op::RBM as u8) | 0x1a];
+        assert_eq!(written.iter().filter(|w| **w == rbm).count(), 2);
+    }
+
+    #[test]
+    fn receive_without_pending_packet_returns_zero() {
+        let (mut enc, _) = ready_driver();
+        // The mock reads back EPKTCNT as zero.
+        assert_eq!(enc.receive(&mut [0u8; 64]), Ok(None));
+        assert_eq!(SimpleNetwork::receive(&mut enc, &mut [0u8; 64]).unwrap(), 0);
+    }
+
+    #[test]
+    fn mtu_follows_max_frame_length() {
+        let (enc, _) = ready_driver();
+        assert_eq!(enc.mtu(), 1500);
+
+        let (mut enc, spi) = driver();
+        enc.set_max_frame_length(1522).unwrap();
+        spi.respond(&EREVID_B7);
+        let enc = enc
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        assert_eq!(enc.mtu(), 1504);
+    }
+
+    #[test]
+    fn set_receive_filter_translates_mode() {
+        let (mut enc, spi) = ready_driver();
+        let erxfcon = |enc: &mut _, mode| {
+            SimpleNetwork::set_receive_filter(enc, mode).unwrap();
+            spi.take_written().last().unwrap()[1]
+        };
+
+        let mode = FilterMode::UNICAST | FilterMode::BROADCAST;
+        assert_eq!(erxfcon(&mut enc, mode), 0b1010_0001);
+        assert_eq!(erxfcon(&mut enc, FilterMode::PROMISCUOUS | mode), 0);
+        // Unicast and broadcast at once, which no frame is.
+        assert_eq!(erxfcon(&mut enc, FilterMode::empty()), 0b1110_0001);
     }
 }
@@ -0,0 +1,1053 @@
+use core::cmp::min;
+use core::marker::PhantomData;
+
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::{Operation, SpiDevice};
+
+use super::config::{Duplex, Enc28j60Config, InterPacketGap, TransmitOptions};
+use super::error::{Error, InitError};
+use super::filter::ReceiveFilter;
+use super::register::*;
+use super::sequence::{self, Step, TransmitAction, TransmitRetry};
+use super::spi_device::{
+    BUFFER_END, DEFAULT_MAC_ADDRESS, DEFAULT_MAX_FRAME_LENGTH, DEFAULT_POLL_LIMIT, MAADR,
+    MAX_FRAME_LENGTH_RANGE, RBM_COMMAND, RX_END, RX_START, ReceiveHeader, SRC_COMMAND, WBM_COMMAND,
+    bank_switch_commands,
+};
+use super::state::{Ready, Uninit};
+use super::status::{InterruptFlags, LinkStatus, ReceivedPacket, Stats, TxState, TxStatus};
+
+/// An asynchronous variant of [`Enc28j60`](crate::Enc28j60) for `embedded-hal-async` SPI devices.
+///
+/// It offers the core of the blocking driver: initialization, recovery, register access, and
+/// receiving and transmitting frames. Both drivers run the same register sequences; see the
+/// blocking driver for details on each operation.
+pub struct Enc28j60Async<SPI: SpiDevice, INT: InputPin, RST: OutputPin, S = Uninit> {
+    /// An SPI device
+    spi: SPI,
+
+    /// Interrupt pin
+    int: INT,
+
+    /// Reset pin
+    reset: RST,
+
+    /// Current bank, or `None` if unknown after a failed transfer.
+    current_bank: Option<Bank>,
+
+    /// Next packet pointer,
+    next_packet: u16,
+
+    /// Start of the receive buffer, as programmed into ERXST,
+    rx_start: u16,
+
+    /// End of the receive buffer, as programmed into ERXND,
+    rx_end: u16,
+
+    /// Maximum number of register reads while polling for the device,
+    poll_limit: u32,
+
+    /// Whether to reset the transmit logic before each transmission,
+    reset_tx_logic: bool,
+
+    /// Number of times to retry an aborted transmission,
+    tx_retries: u8,
+
+    /// Maximum length of a frame, including the CRC,
+    max_frame_len: u16,
+
+    /// Inter-packet gaps, or `None` for the recommended ones,
+    inter_packet_gap: Option<InterPacketGap>,
+
+    /// Half-duplex deferral and backoff settings,
+    macon4: Macon4,
+
+    /// Station MAC address,
+    mac_address: [u8; 6],
+
+    /// Duplex mode,
+    duplex: Duplex,
+
+    /// Receive filters last programmed into ERXFCON,
+    receive_filter: ReceiveFilter,
+
+    /// Whether a transmission was started and its completion not yet reported,
+    tx_pending: bool,
+
+    /// Frame counters,
+    stats: Stats,
+
+    /// Initialization state,
+    state: PhantomData<S>,
+}
+
+impl<SPI, INT, RST> Enc28j60Async<SPI, INT, RST, Uninit>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    pub fn new(spi: SPI, int: INT, reset: RST) -> Self {
+        Enc28j60Async {
+            spi,
+            int,
+            reset,
+            current_bank: Some(Bank::Bank0),
+            next_packet: 0,
+            rx_start: 0,
+            rx_end: 0,
+            poll_limit: DEFAULT_POLL_LIMIT,
+            reset_tx_logic: true,
+            tx_retries: 0,
+            max_frame_len: DEFAULT_MAX_FRAME_LENGTH,
+            inter_packet_gap: None,
+            macon4: Macon4::empty(),
+            mac_address: DEFAULT_MAC_ADDRESS,
+            duplex: Duplex::Full,
+            receive_filter: ReceiveFilter::empty(),
+            tx_pending: false,
+            stats: Stats::default(),
+            state: PhantomData,
+        }
+    }
+
+    /// See [`Enc28j60::set_max_frame_length`](crate::Enc28j60::set_max_frame_length).
+    pub fn set_max_frame_length(&mut self, len: u16) -> Result<(), Error<SPI::Error>> {
+        if !MAX_FRAME_LENGTH_RANGE.contains(&len) {
+            return Err(Error::InvalidArgument);
+        }
+        self.max_frame_len = len;
+        Ok(())
+    }
+
+    /// See [`Enc28j60::set_inter_packet_gap`](crate::Enc28j60::set_inter_packet_gap).
+    pub fn set_inter_packet_gap(&mut self, gap: InterPacketGap) {
+        self.inter_packet_gap = Some(gap);
+    }
+}
+
+impl<SPI, INT, RST, S> Enc28j60Async<SPI, INT, RST, S>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    /// See [`Enc28j60::set_poll_limit`](crate::Enc28j60::set_poll_limit).
+    pub fn set_poll_limit(&mut self, limit: u32) {
+        self.poll_limit = limit;
+    }
+
+    /// See [`Enc28j60::set_transmit_reset`](crate::Enc28j60::set_transmit_reset).
+    pub fn set_transmit_reset(&mut self, enabled: bool) {
+        self.reset_tx_logic = enabled;
+    }
+
+    /// See [`Enc28j60::set_transmit_retries`](crate::Enc28j60::set_transmit_retries).
+    pub fn set_transmit_retries(&mut self, retries: u8) {
+        self.tx_retries = retries;
+    }
+
+    /// See [`Enc28j60::max_frame_length`](crate::Enc28j60::max_frame_length).
+    pub fn max_frame_length(&self) -> u16 {
+        self.max_frame_len
+    }
+
+    /// See [`Enc28j60::stats`](crate::Enc28j60::stats).
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// See [`Enc28j60::reset_stats`](crate::Enc28j60::reset_stats).
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// See [`Enc28j60::into_inner`](crate::Enc28j60::into_inner).
+    pub fn into_inner(self) -> (SPI, INT, RST) {
+        (self.spi, self.int, self.reset)
+    }
+
+    /// An alias for [`Enc28j60Async::into_inner`].
+    pub fn free(self) -> (SPI, INT, RST) {
+        self.into_inner()
+    }
+
+    /// See [`Enc28j60::spi_mut`](crate::Enc28j60::spi_mut).
+    pub fn spi_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// See [`Enc28j60::resync_bank`](crate::Enc28j60::resync_bank).
+    pub async fn resync_bank(&mut self) -> Result<Bank, Error<SPI::Error>> {
+        let bank = self.read_reg::<Econ1>(ECON1).await?.bank();
+        self.current_bank = Some(bank);
+        Ok(bank)
+    }
+
+    /// See [`Enc28j60::invalidate_bank_cache`](crate::Enc28j60::invalidate_bank_cache).
+    pub fn invalidate_bank_cache(&mut self) {
+        self.current_bank = None;
+    }
+
+    /// Resets and configures the device. See [`Enc28j60::initialize`](crate::Enc28j60::initialize).
+    pub async fn initialize<D: DelayNs>(
+        self,
+        delay: &mut D,
+        mac: &[u8; 6],
+        duplex: Duplex,
+    ) -> Result<
+        Enc28j60Async<SPI, INT, RST, Ready>,
+        InitError<Enc28j60Async<SPI, INT, RST, Uninit>, SPI::Error>,
+    > {
+        let config = Enc28j60Config {
+            mac_address: *mac,
+            duplex,
+            receive_filter: ReceiveFilter::empty(),
+            ..self.config()
+        };
+        self.initialize_with(delay, &config).await
+    }
+
+    /// Resets the device and configures it according to `config`. See
+    /// [`Enc28j60::initialize_with`](crate::Enc28j60::initialize_with).
+    pub async fn initialize_with<D: DelayNs>(
+        mut self,
+        delay: &mut D,
+        config: &Enc28j60Config,
+    ) -> Result<
+        Enc28j60Async<SPI, INT, RST, Ready>,
+        InitError<Enc28j60Async<SPI, INT, RST, Uninit>, SPI::Error>,
+    > {
+        match self.configure(delay, config).await {
+            Ok(()) => Ok(self.into_state()),
+            Err(error) => Err(InitError {
+                driver: self.into_state(),
+                error,
+            }),
+        }
+    }
+
+    /// Resets the device and configures it according to `config`. See
+    /// [`Enc28j60Async::initialize_with`].
+    async fn configure<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        config: &Enc28j60Config,
+    ) -> Result<(), Error<SPI::Error>> {
+        let registers = config.registers()?;
+        self.max_frame_len = registers.max_frame_length;
+        self.inter_packet_gap = config.inter_packet_gap;
+        self.macon4 = config.macon4;
+        self.poll_limit = config.poll_limit;
+        self.reset_tx_logic = config.transmit_reset;
+        self.tx_retries = config.transmit_retries;
+        self.duplex = config.duplex;
+
+        self.reset_via_spi(delay).await?;
+
+        // ESTAT.CLKRDY is not reliable after a soft reset on any known revision (see the errata),
+        // so the 1 ms wait above stands in for it.
+        sequence::check_revision(self.read_control(EREVID).await?)?;
+
+        self.run(sequence::configure(config, &registers)).await?;
+        self.next_packet = RX_START;
+        self.rx_start = RX_START;
+        self.rx_end = RX_END;
+        self.mac_address = config.mac_address;
+        self.receive_filter = config.receive_filter;
+        Ok(())
+    }
+
+    /// Returns the configuration the driver and device currently have.
+    fn config(&self) -> Enc28j60Config {
+        Enc28j60Config {
+            mac_address: self.mac_address,
+            duplex: self.duplex,
+            receive_filter: self.receive_filter,
+            max_frame_length: self.max_frame_len,
+            inter_packet_gap: self.inter_packet_gap,
+            macon4: self.macon4,
+            poll_limit: self.poll_limit,
+            transmit_reset: self.reset_tx_logic,
+            transmit_retries: self.tx_retries,
+        }
+    }
+
+    /// Issues a system reset via the device's reset pin, and waits for ESTAT.CLKRDY. See
+    /// [`Enc28j60::reset`](crate::Enc28j60::reset).
+    ///
+    /// Unlike the blocking driver, this is private: the device must be configured again
+    /// afterwards, which only `initialize` and `recover` do.
+    async fn reset<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<SPI::Error>> {
+        self.tx_pending = false;
+        // A reset clears ECON1, selecting bank 0.
+        self.current_bank = Some(Bank::Bank0);
+
+        // Hold the RESET pin low for at least $t_{RSTLOW}$ ns
+        self.reset.set_low().map_err(|_| Error::Pin)?;
+        delay.delay_ns(400).await;
+        self.reset.set_high().map_err(|_| Error::Pin)?;
+
+        // After a System Reset, all PHY registers should not be read or written to until at least
+        // 50 μs have passed since the Reset has ended.
+        delay.delay_us(50).await;
+
+        // Unlike after a soft reset, ESTAT.CLKRDY is reliable after a hardware reset.
+        let clkrdy = Estat::CLKRDY.bits();
+        self.poll_until(ESTAT, clkrdy, clkrdy).await
+    }
+
+    /// Issues a System Soft Reset via SPI, and waits 1 ms. See
+    /// [`Enc28j60::reset_via_spi`](crate::Enc28j60::reset_via_spi).
+    async fn reset_via_spi<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<SPI::Error>> {
+        self.tx_pending = false;
+        // A reset clears ECON1, selecting bank 0.
+        self.current_bank = Some(Bank::Bank0);
+
+        let result = self.spi.write(&[SRC_COMMAND]).await;
+        self.forget_bank_on_error(result)?;
+
+        // The errata sheet asks for a 1 ms wait after the reset, which also covers the 50 μs PHY
+        // access guard.
+        delay.delay_ms(1).await;
+        Ok(())
+    }
+
+    /// Moves the driver into the state `T`, keeping everything else.
+    fn into_state<T>(self) -> Enc28j60Async<SPI, INT, RST, T> {
+        Enc28j60Async {
+            spi: self.spi,
+            int: self.int,
+            reset: self.reset,
+            current_bank: self.current_bank,
+            next_packet: self.next_packet,
+            rx_start: self.rx_start,
+            rx_end: self.rx_end,
+            poll_limit: self.poll_limit,
+            reset_tx_logic: self.reset_tx_logic,
+            tx_retries: self.tx_retries,
+            max_frame_len: self.max_frame_len,
+            inter_packet_gap: self.inter_packet_gap,
+            macon4: self.macon4,
+            mac_address: self.mac_address,
+            duplex: self.duplex,
+            receive_filter: self.receive_filter,
+            tx_pending: self.tx_pending,
+            stats: self.stats,
+            state: PhantomData,
+        }
+    }
+
+    async fn mem_read(&mut self, data: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        let mut ops = [Operation::Write(&[RBM_COMMAND]), Operation::Read(data)];
+        let result = self.spi.transaction(&mut ops).await;
+        self.forget_bank_on_error(result)
+    }
+
+    async fn mem_write(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        let mut ops = [Operation::Write(&[WBM_COMMAND]), Operation::Write(data)];
+        let result = self.spi.transaction(&mut ops).await;
+        self.forget_bank_on_error(result)
+    }
+
+    /// See [`Enc28j60::read_memory`](crate::Enc28j60::read_memory).
+    pub async fn read_memory(
+        &mut self,
+        addr: u16,
+        buf: &mut [u8],
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_u16(ERDPTL, ERDPTH, addr & BUFFER_END).await?;
+        self.mem_read(buf).await
+    }
+
+    /// See [`Enc28j60::write_memory`](crate::Enc28j60::write_memory).
+    pub async fn write_memory(&mut self, addr: u16, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.write_u16(EWRPTL, EWRPTH, addr & BUFFER_END).await?;
+        self.mem_write(data).await
+    }
+
+    /// See [`Enc28j60::read_control`](crate::Enc28j60::read_control).
+    pub async fn read_control(&mut self, reg: ControlRegister) -> Result<u8, Error<SPI::Error>> {
+        if let Some(bank) = reg.bank()
+            && self.current_bank != Some(bank)
+        {
+            self.set_bank(bank).await?;
+        }
+
+        let mut buf = [0u8; 3];
+        let command = [reg.opcode(Op::RCR), 0u8];
+
+        let result = self.spi.transfer(&mut buf, &command).await;
+        self.forget_bank_on_error(result)?;
+
+        if reg.shifts_dummy_byte() {
+            Ok(buf[2])
+        } else {
+            Ok(buf[1])
+        }
+    }
+
+    /// See [`Enc28j60::read_mac_address`](crate::Enc28j60::read_mac_address).
+    pub async fn read_mac_address(&mut self) -> Result<[u8; 6], Error<SPI::Error>> {
+        let mut mac = [0u8; 6];
+        for (reg, octet) in MAADR.into_iter().zip(&mut mac) {
+            *octet = self.read_control(reg).await?;
+        }
+        Ok(mac)
+    }
+
+    /// See [`Enc28j60::read_reg`](crate::Enc28j60::read_reg).
+    pub async fn read_reg<T: From<u8>>(
+        &mut self,
+        reg: ControlRegister,
+    ) -> Result<T, Error<SPI::Error>> {
+        Ok(T::from(self.read_control(reg).await?))
+    }
+
+    async fn write_control(
+        &mut self,
+        reg: ControlRegister,
+        data: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(bank) = reg.bank()
+            && self.current_bank != Some(bank)
+        {
+            self.set_bank(bank).await?;
+        }
+
+        let buf = [reg.opcode(Op::WCR), data];
+        let result = self.spi.write(&buf).await;
+        self.forget_bank_on_error(result)
+    }
+
+    /// See [`Enc28j60::read_phy`](crate::Enc28j60::read_phy).
+    pub async fn read_phy(&mut self, reg: PhyRegister) -> Result<u16, Error<SPI::Error>> {
+        self.write_control(MIREGADR, reg.addr()).await?;
+        self.write_control(MICMD, 0b01).await?;
+        self.poll_until(MISTAT, 0b01, 0).await?;
+        self.write_control(MICMD, 0b00).await?;
+        self.read_u16(MIRDL, MIRDH).await
+    }
+
+    async fn write_phy(&mut self, reg: PhyRegister, data: u16) -> Result<(), Error<SPI::Error>> {
+        self.write_control(MIREGADR, reg.addr()).await?;
+        self.write_u16(MIWRL, MIWRH, data).await
+    }
+
+    //
+    // Helper function
+    //
+
+    async fn poll_until(
+        &mut self,
+        reg: ControlRegister,
+        mask: u8,
+        expected: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        for _ in 0..self.poll_limit {
+            if (self.read_control(reg).await? & mask) == expected {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    async fn read_u16(
+        &mut self,
+        lo: ControlRegister,
+        hi: ControlRegister,
+    ) -> Result<u16, Error<SPI::Error>> {
+        let lo = self.read_control(lo).await? as u16;
+        let hi = self.read_control(hi).await? as u16;
+        Ok(lo | (hi << 8))
+    }
+
+    async fn write_u16(
+        &mut self,
+        lo: ControlRegister,
+        hi: ControlRegister,
+        val: u16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_control(lo, (val & 0xff) as u8).await?;
+        self.write_control(hi, (val >> 8) as u8).await?;
+        Ok(())
+    }
+
+    async fn bit_field_set(
+        &mut self,
+        reg: ControlRegister,
+        mask: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(bank) = reg.bank()
+            && self.current_bank != Some(bank)
+        {
+            self.set_bank(bank).await?;
+        }
+
+        let cmd = [reg.opcode(Op::BFS), mask];
+        let result = self.spi.write(&cmd).await;
+        self.forget_bank_on_error(result)
+    }
+
+    async fn bit_field_clear(
+        &mut self,
+        reg: ControlRegister,
+        mask: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        if let Some(bank) = reg.bank()
+            && self.current_bank != Some(bank)
+        {
+            self.set_bank(bank).await?;
+        }
+
+        let cmd = [reg.opcode(Op::BFC), mask];
+        let result = self.spi.write(&cmd).await;
+        self.forget_bank_on_error(result)
+    }
+
+    async fn set_bank(&mut self, bank: Bank) -> Result<(), Error<SPI::Error>> {
+        // Until both writes succeed, the selected bank is unknown.
+        for command in bank_switch_commands(self.current_bank.take(), bank)
+            .into_iter()
+            .flatten()
+        {
+            self.spi.write(&command).await?;
+        }
+        self.current_bank = Some(bank);
+
+        Ok(())
+    }
+
+    /// Forgets the selected bank if `result` is an error, since a transfer which failed midway
+    /// may have left the device in any bank.
+    fn forget_bank_on_error<T>(
+        &mut self,
+        result: Result<T, SPI::Error>,
+    ) -> Result<T, Error<SPI::Error>> {
+        if result.is_err() {
+            self.current_bank = None;
+        }
+        Ok(result?)
+    }
+
+    /// Runs the register accesses of a sequence shared with the blocking driver.
+    async fn run(
+        &mut self,
+        steps: impl IntoIterator<Item = Step>,
+    ) -> Result<(), Error<SPI::Error>> {
+        for step in steps {
+            match step {
+                Step::Write(reg, data) => self.write_control(reg, data).await?,
+                Step::Set(reg, mask) => self.bit_field_set(reg, mask).await?,
+                Step::Clear(reg, mask) => self.bit_field_clear(reg, mask).await?,
+                Step::WritePhy(reg, data) => self.write_phy(reg, data).await?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, INT, RST> Enc28j60Async<SPI, INT, RST, Ready>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    /// Resets the device through the reset pin and initializes it again with the current
+    /// configuration. See [`Enc28j60::recover`](crate::Enc28j60::recover).
+    pub async fn recover<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<SPI::Error>> {
+        let config = self.config();
+        self.reset(delay).await?;
+        self.configure(delay, &config).await
+    }
+
+    //
+    // Network function
+    //
+
+    /// See [`Enc28j60::receive_filter`](crate::Enc28j60::receive_filter).
+    pub fn receive_filter(&self) -> ReceiveFilter {
+        self.receive_filter
+    }
+
+    /// See [`Enc28j60::set_receive_filter`](crate::Enc28j60::set_receive_filter).
+    pub async fn set_receive_filter(
+        &mut self,
+        filter: ReceiveFilter,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_control(ERXFCON, filter.bits()).await?;
+        self.receive_filter = filter;
+        Ok(())
+    }
+
+    /// See [`Enc28j60::link_up`](crate::Enc28j60::link_up).
+    pub async fn link_up(&mut self) -> Result<bool, Error<SPI::Error>> {
+        Ok(LinkStatus::from_phstat2(self.read_phy(PHSTAT2).await?).up)
+    }
+
+    /// See [`Enc28j60::link_down_latched`](crate::Enc28j60::link_down_latched).
+    pub async fn link_down_latched(&mut self) -> Result<bool, Error<SPI::Error>> {
+        let phstat1 = PhStat1::from(self.read_phy(PHSTAT1).await?);
+        Ok(!phstat1.contains(PhStat1::LLSTAT))
+    }
+
+    /// Receive a single packet into `buf`. See [`Enc28j60::receive`](crate::Enc28j60::receive).
+    pub async fn receive(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<ReceivedPacket>, Error<SPI::Error>> {
+        let Some((header, packet)) = self.peek_packet(buf).await? else {
+            return Ok(None);
+        };
+
+        // The rest of a truncated packet need not be skipped, since ERDPT is set from the next
+        // packet pointer on each receive.
+        self.release_packet(&header).await?;
+        Ok(Some(packet))
+    }
+
+    /// Like [`Enc28j60Async::receive`], but leaves the packet in the receive buffer. See
+    /// [`Enc28j60::peek`](crate::Enc28j60::peek).
+    pub async fn peek(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<ReceivedPacket>, Error<SPI::Error>> {
+        Ok(self.peek_packet(buf).await?.map(|(_, packet)| packet))
+    }
+
+    /// Like [`Enc28j60Async::peek`], but also returns the header of the packet, so that it can be
+    /// released with [`Enc28j60Async::release_packet`] without reading the header again.
+    pub(crate) async fn peek_packet(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<(ReceiveHeader, ReceivedPacket)>, Error<SPI::Error>> {
+        let Some(header) = self.read_receive_header().await? else {
+            return Ok(None);
+        };
+
+        let copy_len = min(header.len, buf.len());
+        if copy_len > 0 {
+            self.mem_read(&mut buf[..copy_len]).await?;
+        }
+
+        let packet = header.packet(copy_len);
+        Ok(Some((header, packet)))
+    }
+
+    /// Drops the next pending packet without reading it. See
+    /// [`Enc28j60::consume`](crate::Enc28j60::consume).
+    pub async fn consume(&mut self) -> Result<(), Error<SPI::Error>> {
+        if let Some(header) = self.read_receive_header().await? {
+            self.release_packet(&header).await?;
+        }
+        Ok(())
+    }
+
+    /// Transmit a packet. See [`Enc28j60::transmit`](crate::Enc28j60::transmit).
+    pub async fn transmit(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        ether_type: u16,
+        data: &[u8],
+    ) -> Result<TxStatus, Error<SPI::Error>> {
+        let options = TransmitOptions::default();
+        self.start_transmit(dst, src, ether_type, data, options)
+            .await?;
+
+        let mut retry = TransmitRetry::new(self.tx_retries, self.poll_limit);
+        loop {
+            match retry.next(self.poll_transmit().await?) {
+                TransmitAction::Poll => {}
+                TransmitAction::Retry => {
+                    self.run(sequence::reset_transmit()).await?;
+                    self.arm_transmit().await?;
+                }
+                TransmitAction::Finish(result) => return result,
+            }
+        }
+    }
+
+    /// Writes a frame into the transmit buffer and starts transmitting it, without waiting for
+    /// the transmission to complete. See
+    /// [`Enc28j60::start_transmit`](crate::Enc28j60::start_transmit).
+    pub async fn start_transmit(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        ether_type: u16,
+        data: &[u8],
+        options: TransmitOptions,
+    ) -> Result<(), Error<SPI::Error>> {
+        let frame_len = dst.len() + src.len() + 2 + data.len();
+        sequence::check_frame_length(frame_len, self.max_frame_len, &options)?;
+
+        let txrts = Econ1::TXRTS;
+        if self.tx_pending || self.read_reg::<Econ1>(ECON1).await?.contains(txrts) {
+            return Err(Error::Busy);
+        }
+
+        if self.reset_tx_logic {
+            self.run(sequence::reset_transmit()).await?;
+        }
+
+        let tx_start = self.read_u16(ETXSTL, ETXSTH).await?;
+        let tx_end = sequence::transmit_end(tx_start, frame_len)?;
+
+        self.write_u16(EWRPTL, EWRPTH, tx_start).await?;
+        self.mem_write(&[options.control_byte()]).await?;
+        self.mem_write(dst).await?;
+        self.mem_write(src).await?;
+        self.mem_write(&ether_type.to_be_bytes()).await?;
+        self.mem_write(data).await?;
+        self.write_u16(ETXNDL, ETXNDH, tx_end).await?;
+
+        self.arm_transmit().await
+    }
+
+    /// Checks on a transmission started with [`Enc28j60Async::start_transmit`]. See
+    /// [`Enc28j60::poll_transmit`](crate::Enc28j60::poll_transmit).
+    pub async fn poll_transmit(&mut self) -> Result<TxState, Error<SPI::Error>> {
+        if self.read_reg::<Econ1>(ECON1).await?.contains(Econ1::TXRTS) {
+            return Ok(TxState::InProgress);
+        }
+
+        let first_report = core::mem::take(&mut self.tx_pending);
+        let estat: Estat = self.read_reg(ESTAT).await?;
+
+        let tx_end = self.read_u16(ETXNDL, ETXNDH).await?;
+        self.write_u16(ERDPTL, ERDPTH, tx_end + 1).await?;
+        let mut tsv = [0u8; TxStatus::LEN];
+        self.mem_read(&mut tsv).await?;
+        let state = sequence::transmit_state(estat, TxStatus::from_bytes(tsv));
+
+        if let TxState::Aborted(_) = state {
+            self.run(sequence::clear_transmit_abort()).await?;
+        }
+        if first_report {
+            self.stats.count_transmit_state(&state);
+        }
+        Ok(state)
+    }
+
+    //
+    // Helper function
+    //
+
+    /// Starts transmitting the frame between ETXST and ETXND.
+    async fn arm_transmit(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.run(sequence::arm_transmit()).await?;
+        self.tx_pending = true;
+        Ok(())
+    }
+
+    /// Reads the receive status vector of the next pending packet. See
+    /// [`Enc28j60::receive`](crate::Enc28j60::receive) for the errors.
+    async fn read_receive_header(&mut self) -> Result<Option<ReceiveHeader>, Error<SPI::Error>> {
+        if self.read_control(EPKTCNT).await? == 0 {
+            return Ok(None);
+        }
+
+        let rx_error = InterruptFlags::RX_ERROR;
+        let overflowed = self
+            .read_reg::<InterruptFlags>(EIR)
+            .await?
+            .contains(rx_error);
+        if overflowed {
+            self.bit_field_clear(EIR, rx_error.clear_mask()).await?;
+            self.stats.count_overflow();
+        }
+
+        self.write_u16(ERDPTL, ERDPTH, self.next_packet).await?;
+        let mut rsv = [0u8; 6];
+        self.mem_read(&mut rsv).await?;
+
+        let Some(header) = ReceiveHeader::parse(rsv, overflowed, self.rx_start..=self.rx_end)
+        else {
+            self.reset_receive_logic().await?;
+            return Err(Error::CorruptReceiveBuffer);
+        };
+
+        if header.runt {
+            self.release_packet(&header).await?;
+            return Err(Error::RuntFrame);
+        }
+        Ok(Some(header))
+    }
+
+    /// Frees the memory of the packet just read and moves on to the packet after it, counting it
+    /// in the statistics.
+    pub(crate) async fn release_packet(
+        &mut self,
+        header: &ReceiveHeader,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.stats.count_received(header.status);
+
+        self.next_packet = header.next_packet;
+        self.run(sequence::release_packet(
+            header.next_packet,
+            self.rx_start,
+            self.rx_end,
+        ))
+        .await
+    }
+
+    /// Resets the receive logic and empties the receive buffer, after its contents can no longer
+    /// be trusted.
+    async fn reset_receive_logic(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.run(sequence::reset_receive()).await?;
+        for _ in 0..self.read_control(EPKTCNT).await? {
+            self.run([sequence::decrement_packet_count()]).await?;
+        }
+        self.run(sequence::restart_receive(self.rx_start)).await?;
+        self.next_packet = self.rx_start;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::mock::{
+        EREVID_B7, MockSpi, NoDelay, async_driver, async_ready_driver, block_on, driver,
+        ready_driver,
+    };
+
+    fn wcr(reg: ControlRegister, data: u8) -> Vec<u8> {
+        vec![reg.opcode(Op::WCR), data]
+    }
+
+    /// Queues the responses for a transmission which is aborted once and then succeeds.
+    fn respond_abort_then_success(spi: &MockSpi) {
+        // ECON1, ETXST
+        spi.respond(&[0; 9]);
+        // ECON1, ESTAT with TXABRT set, ETXND, transmit status vector
+        spi.respond(&[0, 0, 0, 0, 0b10, 0]);
+        spi.respond(&[0; 13]);
+        // ECON1, ESTAT, ETXND, then a transmit status vector reporting success
+        spi.respond(&[0; 12]);
+        spi.respond(&[0x40, 0, 0x80, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn initialize_matches_blocking_driver() {
+        for duplex in [Duplex::Full, Duplex::Half] {
+            let config = Enc28j60Config {
+                mac_address: [0x02, 0, 0, 0, 0, 1],
+                duplex,
+                receive_filter: ReceiveFilter::UNICAST | ReceiveFilter::CRC_CHECK,
+                ..Enc28j60Config::default()
+            };
+
+            let (enc, spi) = driver();
+            spi.respond(&EREVID_B7);
+            enc.initialize_with(&mut NoDelay, &config).unwrap();
+
+            let (async_enc, async_spi) = async_driver();
+            async_spi.respond(&EREVID_B7);
+            let async_enc = block_on(async_enc.initialize_with(&mut NoDelay, &config))
+                .map_err(|e| e.error)
+                .unwrap();
+
+            let written = async_spi.take_written();
+            assert_eq!(written, spi.take_written());
+            assert_eq!(written[0], [SRC_COMMAND]);
+            assert!(written.contains(&wcr(MAADR6, 0x01)));
+            assert_eq!(async_enc.receive_filter(), config.receive_filter);
+        }
+    }
+
+    #[test]
+    fn initialize_can_be_retried_after_failure() {
+        let (enc, spi) = async_driver();
+        // EREVID reads back as 00h.
+        let error = block_on(enc.initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full))
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(error.error, Error::DeviceNotResponding);
+
+        spi.take_written();
+        spi.respond(&EREVID_B7);
+        let mut enc = block_on(error.driver.initialize(
+            &mut NoDelay,
+            &DEFAULT_MAC_ADDRESS,
+            Duplex::Full,
+        ))
+        .map_err(|e| e.error)
+        .unwrap();
+        assert_eq!(block_on(enc.receive(&mut [0u8; 8])), Ok(None));
+    }
+
+    #[test]
+    fn receive_frees_buffer_up_to_next_packet() {
+        let (mut enc, spi) = async_ready_driver();
+        // EPKTCNT, EIR
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        // Receive status vector: next packet at 0x0040, 4 bytes and the CRC, received OK
+        spi.respond(&[0x40, 0x00, 0x08, 0x00, 0x80, 0x00]);
+        spi.respond(&[1, 2, 3, 4]);
+
+        let mut buf = [0u8; 8];
+        let packet = block_on(enc.receive(&mut buf)).unwrap().unwrap();
+        assert_eq!((packet.copied, packet.total), (4, 4));
+        assert!(packet.status.received_ok());
+        assert_eq!(buf[..4], [1, 2, 3, 4]);
+        assert_eq!(enc.stats().rx_frames, 1);
+
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(ERXRDPTL, 0x3f)));
+        assert!(written.contains(&wcr(ERXRDPTH, 0x00)));
+        assert!(written.ends_with(&[vec![ECON2.opcode(Op::BFS), Econ2::PKTDEC.bits()]]));
+
+        // No packet is pending.
+        assert_eq!(block_on(enc.receive(&mut buf)), Ok(None));
+    }
+
+    #[test]
+    fn peek_leaves_packet_pending() {
+        let (mut enc, spi) = async_ready_driver();
+        // EPKTCNT, EIR, then the receive status vector of a 4-byte frame, followed by the next
+        // packet at 0020h
+        let header = [[0, 1, 0, 0, 0, 0], [0x20, 0x00, 0x08, 0x00, 0x80, 0x00]].concat();
+        spi.respond(&header);
+        spi.respond(&[1, 2, 3, 4]);
+
+        let mut peeked = [0u8; 4];
+        let packet = block_on(enc.peek(&mut peeked)).unwrap().unwrap();
+        assert_eq!((packet.copied, packet.total), (4, 4));
+        assert_eq!(peeked, [1, 2, 3, 4]);
+        assert_eq!(enc.next_packet, 0);
+        assert_eq!(enc.stats().rx_frames, 0);
+
+        // `consume` reads the header again.
+        spi.respond(&header);
+        block_on(enc.consume()).unwrap();
+        assert!(spi.take_written().contains(&wcr(ERXRDPTL, 0x1f)));
+        assert_eq!(enc.next_packet, 0x20);
+        assert_eq!(enc.stats().rx_frames, 1);
+    }
+
+    #[test]
+    fn receive_rejects_next_packet_outside_rx_buffer() {
+        let (mut enc, spi) = async_ready_driver();
+        // EPKTCNT, EIR
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        // Receive status vector pointing into the transmit buffer
+        spi.respond(&[0x00, 0x10, 0x08, 0x00, 0x80, 0x00]);
+
+        let result = block_on(enc.receive(&mut [0u8; 8]));
+        assert_eq!(result, Err(Error::CorruptReceiveBuffer));
+        let written = spi.take_written();
+        assert!(written.contains(&vec![ECON1.opcode(Op::BFS), Econ1::RXRST.bits()]));
+        assert!(written.ends_with(&[vec![ECON1.opcode(Op::BFS), Econ1::RXEN.bits()]]));
+        assert_eq!(enc.next_packet, RX_START);
+    }
+
+    #[test]
+    fn transmit_matches_blocking_driver() {
+        let dst = [0xff; 6];
+        let data = [0x5a; 46];
+
+        let (mut enc, spi) = ready_driver();
+        enc.set_transmit_retries(1);
+        respond_abort_then_success(&spi);
+        let status = enc
+            .transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &data)
+            .unwrap();
+
+        let (mut async_enc, async_spi) = async_ready_driver();
+        async_enc.set_transmit_retries(1);
+        respond_abort_then_success(&async_spi);
+        let async_status = block_on(async_enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &data));
+
+        assert_eq!(async_status, Ok(status));
+        assert_eq!(async_spi.take_written(), spi.take_written());
+        assert_eq!(async_enc.stats(), enc.stats());
+        assert_eq!(async_enc.stats().tx_aborts, 1);
+        assert_eq!(async_enc.stats().tx_frames, 1);
+    }
+
+    #[test]
+    fn transmit_reports_abort_without_retries() {
+        let (mut enc, spi) = async_ready_driver();
+        // ECON1, ETXST
+        spi.respond(&[0; 9]);
+        // ECON1, ESTAT with LATECOL set, ETXND
+        spi.respond(&[0, 0, 0, 0, 0b1_0000, 0]);
+        spi.respond(&[0; 6]);
+        // Transmit status vector with a late collision
+        spi.respond(&[0, 0, 1, 0x20, 0, 0, 0]);
+        let dst = [0xff; 6];
+        let result = block_on(enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46]));
+        assert_eq!(result, Err(Error::LateCollision));
+
+        let written = spi.take_written();
+        assert!(written.ends_with(&[
+            vec![ESTAT.opcode(Op::BFC), 0b1_0010],
+            vec![EIR.opcode(Op::BFC), 0b10]
+        ]));
+    }
+
+    #[test]
+    fn start_transmit_rejects_frame_while_busy() {
+        let (mut enc, spi) = async_ready_driver();
+        let dst = [0xff; 6];
+        let options = TransmitOptions::default();
+        block_on(enc.start_transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46], options))
+            .unwrap();
+        spi.take_written();
+
+        let result = enc.start_transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[1; 46], options);
+        assert_eq!(block_on(result), Err(Error::Busy));
+        assert!(spi.take_written().is_empty());
+
+        // ECON1 with TXRTS still set
+        spi.respond(&[0, Econ1::TXRTS.bits(), 0]);
+        assert_eq!(block_on(enc.poll_transmit()), Ok(TxState::InProgress));
+        // ECON1, ESTAT, ETXND, then the transmit status vector
+        spi.respond(&[0; 12]);
+        spi.respond(&[0x40, 0, 0x80, 0, 0, 0, 0]);
+        assert!(matches!(
+            block_on(enc.poll_transmit()),
+            Ok(TxState::Done(_))
+        ));
+        assert_eq!(enc.stats().tx_frames, 1);
+    }
+
+    #[test]
+    fn recover_reprograms_mac_and_filter() {
+        let (enc, spi) = async_driver();
+        let config = Enc28j60Config {
+            mac_address: [0x02, 0, 0, 0, 0, 1],
+            duplex: Duplex::Half,
+            ..Enc28j60Config::default()
+        };
+        spi.respond(&EREVID_B7);
+        let mut enc = block_on(enc.initialize_with(&mut NoDelay, &config))
+            .map_err(|e| e.error)
+            .unwrap();
+        let filter = ReceiveFilter::UNICAST | ReceiveFilter::BROADCAST;
+        block_on(enc.set_receive_filter(filter)).unwrap();
+        spi.take_written();
+
+        // ESTAT with CLKRDY set
+        spi.respond(&[0, 0b1, 0]);
+        spi.respond(&EREVID_B7);
+        block_on(enc.recover(&mut NoDelay)).unwrap();
+
+        let written = spi.take_written();
+        assert_eq!(written[0], [ESTAT.opcode(Op::RCR), 0]);
+        assert!(written.contains(&vec![SRC_COMMAND]));
+        assert!(written.contains(&wcr(MAADR6, 0x01)));
+        // Half duplex leaves MACON3.FULDPX clear.
+        assert!(written.contains(&wcr(MACON3, 0b0011_0010)));
+        assert!(written.contains(&wcr(ERXFCON, filter.bits())));
+        assert_eq!(enc.receive_filter(), filter);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::error::Error;
+use crate::filter::ReceiveFilter;
+use crate::register::{Macon4, PhCon1, PhCon2};
+use crate::spi_device::{
+    DEFAULT_MAC_ADDRESS, DEFAULT_MAX_FRAME_LENGTH, DEFAULT_POLL_LIMIT, MAX_FRAME_LENGTH_RANGE,
+};
+
+/// The duplex mode of the MAC and PHY.
+///
+/// The ENC28J60 does not support auto-negotiation, so the mode must match the link partner.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Duplex {
+    /// Full-duplex mode, for links to a switch or another host configured for full duplex.
+    #[default]
+    Full,
+    /// Half-duplex mode, for links to a hub or a partner that auto-negotiates.
+    Half,
+}
+
+/// Inter-packet gap settings, as programmed into MABBIPG, MAIPGL and MAIPGH.
+///
+/// Each register holds a 7-bit value; the most significant bit is ignored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InterPacketGap {
+    /// The gap between back-to-back frames (MABBIPG).
+    pub back_to_back: u8,
+    /// The gap between non-back-to-back frames (MAIPGL).
+    pub non_back_to_back: u8,
+    /// The second non-back-to-back gap register (MAIPGH). It is only used in half-duplex mode,
+    /// and is not written in full-duplex mode.
+    pub non_back_to_back_high: u8,
+}
+
+impl InterPacketGap {
+    /// Returns the gaps recommended by the datasheet for `duplex`, all corresponding to 9.6 μs.
+    pub const fn recommended(duplex: Duplex) -> Self {
+        match duplex {
+            Duplex::Full => InterPacketGap {
+                back_to_back: 0x15,
+                non_back_to_back: 0x12,
+                non_back_to_back_high: 0x00,
+            },
+            Duplex::Half => InterPacketGap {
+                back_to_back: 0x12,
+                non_back_to_back: 0x12,
+                non_back_to_back_high: 0x0c,
+            },
+        }
+    }
+}
+
+/// The configuration applied by [`Enc28j60::initialize_with`](crate::Enc28j60::initialize_with).
+///
+/// Start from [`Enc28j60Config::default`] and override the fields that matter to the application:
+///
+/// ```
+/// use enc28j60::{Duplex, Enc28j60Config, ReceiveFilter};
+///
+/// let config = Enc28j60Config {
+///     mac_address: [0x02, 0x00, 0x00, 0x12, 0x34, 0x56],
+///     duplex: Duplex::Half,
+///     receive_filter: ReceiveFilter::UNICAST | ReceiveFilter::BROADCAST | ReceiveFilter::CRC_CHECK,
+///     ..Enc28j60Config::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Enc28j60Config {
+    /// The station MAC address. Defaults to [`DEFAULT_MAC_ADDRESS`].
+    pub mac_address: [u8; 6],
+    /// The duplex mode of the MAC and PHY. Defaults to full duplex.
+    pub duplex: Duplex,
+    /// The receive filter programmed into ERXFCON. Defaults to accepting every frame.
+    pub receive_filter: ReceiveFilter,
+    /// The maximum length of a frame, including the CRC. Defaults to
+    /// [`DEFAULT_MAX_FRAME_LENGTH`]. Initialization fails with
+    /// [`Error::InvalidArgument`](crate::Error::InvalidArgument) if it is outside the range
+    /// accepted by [`Enc28j60::set_max_frame_length`](crate::Enc28j60::set_max_frame_length).
+    pub max_frame_length: u16,
+    /// The inter-packet gaps, or `None` for those recommended for the duplex mode.
+    pub inter_packet_gap: Option<InterPacketGap>,
+    /// The half-duplex deferral and backoff settings. Ignored in full-duplex mode.
+    pub macon4: Macon4,
+    /// The maximum number of register reads while polling. Defaults to [`DEFAULT_POLL_LIMIT`].
+    pub poll_limit: u32,
+    /// Whether to reset the transmit logic before each transmission. Defaults to `true`. See
+    /// [`Enc28j60::set_transmit_reset`](crate::Enc28j60::set_transmit_reset).
+    pub transmit_reset: bool,
+    /// The number of times to retry an aborted transmission. Defaults to 0.
+    pub transmit_retries: u8,
+}
+
+impl Default for Enc28j60Config {
+    fn default() -> Self {
+        Enc28j60Config {
+            mac_address: DEFAULT_MAC_ADDRESS,
+            duplex: Duplex::Full,
+            receive_filter: ReceiveFilter::empty(),
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            inter_packet_gap: None,
+            macon4: Macon4::empty(),
+            poll_limit: DEFAULT_POLL_LIMIT,
+            transmit_reset: true,
+            transmit_retries: 0,
+        }
+    }
+}
+
+impl Enc28j60Config {
+    /// Returns the MAC and PHY register values which apply this configuration, or fails with
+    /// [`Error::InvalidArgument`] if the maximum frame length is out of range.
+    pub(crate) fn registers<E>(&self) -> Result<ConfigRegisters, Error<E>> {
+        if !MAX_FRAME_LENGTH_RANGE.contains(&self.max_frame_length) {
+            return Err(Error::InvalidArgument);
+        }
+
+        // Configure the PADCFG, TXCRCEN and FULDPX bits of MACON3.
+        //
+        // In this setup, we are:
+        // - enabling frame length checking
+        // - appending a CRC to transmitted frames
+        // - padding all short frames to 60 bytes and appending a CRC
+        // - enabling full duplex mode, if requested
+        const MACON3_MASK: u8 = 0b0011_0010;
+        const FULDPX_MASK: u8 = 0b0000_0001;
+        let (macon3, phcon1) = match self.duplex {
+            Duplex::Full => (MACON3_MASK | FULDPX_MASK, PhCon1::PDPXMD),
+            Duplex::Half => (MACON3_MASK, PhCon1::empty()),
+        };
+
+        let gap = self
+            .inter_packet_gap
+            .unwrap_or(InterPacketGap::recommended(self.duplex));
+        Ok(ConfigRegisters {
+            macon3,
+            max_frame_length: self.max_frame_length,
+            back_to_back_gap: gap.back_to_back & 0x7f,
+            non_back_to_back_gap: gap.non_back_to_back & 0x7f,
+            half_duplex: (self.duplex == Duplex::Half)
+                .then_some((gap.non_back_to_back_high & 0x7f, self.macon4)),
+            phcon1,
+            phcon2: PhCon2::HDLDIS,
+        })
+    }
+}
+
+/// The MAC and PHY register values for an [`Enc28j60Config`], as programmed by both drivers.
+pub(crate) struct ConfigRegisters {
+    /// MACON3.
+    pub(crate) macon3: u8,
+    /// MAMXFL.
+    pub(crate) max_frame_length: u16,
+    /// MABBIPG.
+    pub(crate) back_to_back_gap: u8,
+    /// MAIPGL.
+    pub(crate) non_back_to_back_gap: u8,
+    /// MAIPGH and MACON4, which are only written in half-duplex mode.
+    pub(crate) half_duplex: Option<(u8, Macon4)>,
+    /// PHCON1.
+    pub(crate) phcon1: PhCon1,
+    /// PHCON2.
+    pub(crate) phcon2: PhCon2,
+}
+
+/// The function of an LED driven by the PHY, as programmed into PHLCON.LACFG/LBCFG.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum LedMode {
+    /// Display transmit activity (stretchable).
+    Transmit = 0b0001,
+    /// Display receive activity (stretchable).
+    Receive = 0b0010,
+    /// Display collision activity (stretchable).
+    Collision = 0b0011,
+    /// Display link status.
+    Link = 0b0100,
+    /// Display duplex status.
+    Duplex = 0b0101,
+    /// Display transmit and receive activity (stretchable).
+    TransmitReceive = 0b0111,
+    /// Always on.
+    On = 0b1000,
+    /// Always off.
+    Off = 0b1001,
+    /// Blink fast.
+    BlinkFast = 0b1010,
+    /// Blink slow.
+    BlinkSlow = 0b1011,
+    /// Display link status and receive activity (always stretched).
+    LinkReceive = 0b1100,
+    /// Display link status and transmit/receive activity (always stretched).
+    LinkActivity = 0b1101,
+    /// Display duplex status and collision activity (always stretched).
+    DuplexCollision = 0b1110,
+}
+
+/// Per-frame transmit options, written as the per-packet control byte ahead of the frame.
+///
+/// Unless `override_defaults` is set, the frame is padded and a CRC is appended according to
+/// MACON3, and the other fields are ignored. `initialize` configures MACON3 to pad short frames to
+/// 60 bytes and append a CRC.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TransmitOptions {
+    /// Use the fields below for this frame instead of the MACON3 configuration (POVERRIDE).
+    pub override_defaults: bool,
+    /// Append a CRC to the frame (PCRCEN). Clear it for frames that already contain their FCS.
+    pub append_crc: bool,
+    /// Pad frames shorter than 60 bytes with zeros (PPADEN).
+    pub pad: bool,
+    /// Transmit frames longer than the maximum frame length (PHUGEEN).
+    pub allow_huge: bool,
+}
+
+impl TransmitOptions {
+    /// The per-packet control byte for these options.
+    pub const fn control_byte(&self) -> u8 {
+        if !self.override_defaults {
+            return 0;
+        }
+
+        const POVERRIDE: u8 = 1 << 0;
+        const PCRCEN: u8 = 1 << 1;
+        const PPADEN: u8 = 1 << 2;
+        const PHUGEEN: u8 = 1 << 3;
+
+        let mut control = POVERRIDE;
+        if self.append_crc {
+            control |= PCRCEN;
+        }
+        if self.pad {
+            control |= PPADEN;
+        }
+        if self.allow_huge {
+            control |= PHUGEEN;
+        }
+        control
+    }
+}
@@ -0,0 +1,80 @@
+use core::fmt;
+
+/// An error returned by the driver, generic over the SPI error type `E`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// The underlying SPI transfer failed.
+    Spi(E),
+    /// The device did not complete an operation within the configured poll limit.
+    Timeout,
+    /// The device aborted the transmission, and every retry was aborted as well.
+    Aborted,
+    /// Like [`Error::Aborted`], but the last attempt was aborted by a late collision. On a
+    /// half-duplex link, this usually means the link partner runs in full-duplex mode.
+    LateCollision,
+    /// Like [`Error::Aborted`], but the last attempt was aborted after more collisions than
+    /// MACLCON1 allows, on a congested half-duplex link.
+    ExcessiveCollisions,
+    /// The frame does not fit within the maximum frame length or the transmit buffer.
+    FrameTooLong,
+    /// The device has not been initialized.
+    NotInitialized,
+    /// The buffer is too small. The contained `usize` is the required buffer size.
+    BufferTooSmall(usize),
+    /// The reset pin could not be driven.
+    Pin,
+    /// The receive status vector pointed outside the receive buffer. The receive logic was reset,
+    /// dropping the packets in the buffer.
+    CorruptReceiveBuffer,
+    /// The received frame was shorter than its CRC. It was dropped.
+    RuntFrame,
+    /// EREVID read back as 00h or FFh: the device is not responding on the SPI bus.
+    DeviceNotResponding,
+    /// EREVID holds a silicon revision which the driver does not know. The contained `u8` is the
+    /// EREVID value.
+    UnsupportedRevision(u8),
+    /// An argument is outside the range the device supports.
+    InvalidArgument,
+    /// The operation only works in full-duplex mode, but the device runs in half-duplex mode.
+    RequiresFullDuplex,
+    /// A previous transmission has not completed yet, see
+    /// [`Enc28j60::poll_transmit`](crate::Enc28j60::poll_transmit).
+    Busy,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Self {
+        Error::Spi(error)
+    }
+}
+
+/// A failed initialization, which hands the uninitialized driver back along with the error so
+/// that the caller can retry, or reclaim the SPI device and pins.
+pub struct InitError<D, E> {
+    /// The driver, in the [`Uninit`](crate::Uninit) state.
+    pub driver: D,
+    /// The reason the initialization failed.
+    pub error: Error<E>,
+}
+
+impl<D, E: fmt::Debug> fmt::Debug for InitError<D, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InitError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<D, E: defmt::Format> defmt::Format for InitError<D, E> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "InitError {{ error: {} }}", self.error)
+    }
+}
+
+impl<D, E> From<InitError<D, E>> for Error<E> {
+    fn from(error: InitError<D, E>) -> Self {
+        error.error
+    }
+}
@@ -0,0 +1,189 @@
+use core::ops::{BitOr, BitOrAssign};
+
+/// Receive filter configuration, as programmed into `ERXFCON`.
+///
+/// Flags are combined with `|`. When no flag is set, the filters are disabled and every frame is
+/// accepted, including frames with an invalid CRC.
+///
+/// # Combining filters
+///
+/// Unless [`ReceiveFilter::AND`] is set, a frame is accepted when any enabled filter accepts it.
+/// For example, `UNICAST | HASH_TABLE` accepts frames to the local MAC address as well as frames
+/// to any address, unicast or multicast, which hashes to a set bit in the hash table. With
+/// [`ReceiveFilter::AND`], a frame must be accepted by every enabled filter, so the same
+/// combination would only accept frames to the local MAC address whose hash bit is set.
+/// [`ReceiveFilter::MULTICAST`] accepts every multicast frame, which makes the hash table
+/// redundant for multicast when both are enabled without [`ReceiveFilter::AND`].
+///
+/// # CRC check
+///
+/// [`ReceiveFilter::CRC_CHECK`] is not a filter in its own right: it discards frames with an
+/// invalid CRC regardless of which other filters accept them, and regardless of
+/// [`ReceiveFilter::AND`]. Without it, frames with an invalid CRC are delivered and can only be
+/// told apart through the receive status vector.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReceiveFilter(u8);
+
+impl ReceiveFilter {
+    /// Accept broadcast frames (BCEN).
+    pub const BROADCAST: ReceiveFilter = ReceiveFilter(1 << 0);
+    /// Accept frames whose destination has the multicast bit set (MCEN).
+    pub const MULTICAST: ReceiveFilter = ReceiveFilter(1 << 1);
+    /// Accept frames whose destination hashes to a set bit in the hash table (HTEN).
+    pub const HASH_TABLE: ReceiveFilter = ReceiveFilter(1 << 2);
+    /// Accept Magic Packets for the local MAC address (MPEN).
+    pub const MAGIC_PACKET: ReceiveFilter = ReceiveFilter(1 << 3);
+    /// Accept frames matching the pattern match filter (PMEN).
+    pub const PATTERN_MATCH: ReceiveFilter = ReceiveFilter(1 << 4);
+    /// Discard frames with an invalid CRC (CRCEN).
+    pub const CRC_CHECK: ReceiveFilter = ReceiveFilter(1 << 5);
+    /// Require frames to be accepted by every enabled filter, instead of by any of them (ANDOR).
+    pub const AND: ReceiveFilter = ReceiveFilter(1 << 6);
+    /// Accept frames addressed to the local MAC address (UCEN).
+    pub const UNICAST: ReceiveFilter = ReceiveFilter(1 << 7);
+
+    /// No filters enabled. Every frame is accepted.
+    pub const fn empty() -> Self {
+        ReceiveFilter(0)
+    }
+
+    /// Creates a filter from a raw `ERXFCON` value.
+    pub const fn from_bits(bits: u8) -> Self {
+        ReceiveFilter(bits)
+    }
+
+    /// The raw `ERXFCON` value.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Reports whether all flags in `other` are set.
+    pub const fn contains(self, other: ReceiveFilter) -> bool {
+        (self.0 & other.0) == other.0
+    }
+
+    /// Sets the flags in `other`.
+    pub fn insert(&mut self, other: ReceiveFilter) {
+        self.0 |= other.0;
+    }
+
+    /// Clears the flags in `other`.
+    pub fn remove(&mut self, other: ReceiveFilter) {
+        self.0 &= !other.0;
+    }
+}
+
+impl From<u8> for ReceiveFilter {
+    fn from(bits: u8) -> Self {
+        ReceiveFilter(bits)
+    }
+}
+
+impl BitOr for ReceiveFilter {
+    type Output = ReceiveFilter;
+
+    fn bitor(self, rhs: ReceiveFilter) -> ReceiveFilter {
+        ReceiveFilter(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ReceiveFilter {
+    fn bitor_assign(&mut self, rhs: ReceiveFilter) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Computes the hash table index (0–63) that the hash table filter uses for `mac`.
+///
+/// The hardware runs the Ethernet CRC-32 over the six destination address octets and uses bits
+/// 28:23 of the result as the index. Bits 28:26 select one of `EHT0`–`EHT7` and bits 25:23 select
+/// the bit within that register.
+pub const fn multicast_hash_index(mac: &[u8; 6]) -> u8 {
+    const POLYNOMIAL: u32 = 0x04c1_1db7;
+
+    let mut crc = 0xffff_ffffu32;
+    let mut i = 0;
+    while i < mac.len() {
+        let mut octet = mac[i];
+        let mut bit = 0;
+        while bit < 8 {
+            // Octets are shifted in least significant bit first, as they appear on the wire.
+            let feedback = ((crc >> 31) as u8 ^ octet) & 1;
+            crc <<= 1;
+            if feedback != 0 {
+                crc ^= POLYNOMIAL;
+            }
+            octet >>= 1;
+            bit += 1;
+        }
+        i += 1;
+    }
+
+    ((crc >> 23) & 0x3f) as u8
+}
+
+/// Computes the checksum the pattern match filter expects for `window` under `mask`.
+///
+/// The hardware takes the 64-byte window starting `EPMO` bytes into the frame (counting from the
+/// first byte of the destination address), keeps the bytes whose bit is set in `mask` (bit 0
+/// selects the first byte), and computes the IP checksum of RFC 1071 over them as if they were
+/// contiguous: selected bytes are paired into big-endian 16-bit words, an odd trailing byte is
+/// padded with zero, the words are summed in one's complement arithmetic, and the result is
+/// complemented. A frame is accepted when this matches `EPMCS`.
+///
+/// Bytes past the end of `window` are treated as unselected.
+pub fn pattern_match_checksum(window: &[u8], mask: u64) -> u16 {
+    let mut sum = 0u32;
+    let mut high = None;
+    for (i, &byte) in window.iter().take(64).enumerate() {
+        if (mask >> i) & 1 == 0 {
+            continue;
+        }
+        match high.take() {
+            None => high = Some(byte),
+            Some(h) => sum += u32::from(u16::from_be_bytes([h, byte])),
+        }
+    }
+    if let Some(h) = high {
+        sum += u32::from(u16::from_be_bytes([h, 0]));
+    }
+
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+
+    use super::*;
+    use crate::mock::ready_driver;
+    use crate::register::{EHT6, ERXFCON, Op};
+
+    #[test]
+    fn multicast_hash_index_matches_datasheet() {
+        // The example given for the hash table filter in the data sheet.
+        assert_eq!(
+            multicast_hash_index(&[0x01, 0x00, 0x00, 0x00, 0x01, 0x2c]),
+            0x34
+        );
+    }
+
+    #[test]
+    fn add_multicast_group_sets_hash_bit() {
+        let (mut enc, spi) = ready_driver();
+        enc.add_multicast_group(&[0x01, 0x00, 0x00, 0x00, 0x01, 0x2c])
+            .unwrap();
+
+        // Index 0x34 is bit 4 of EHT6.
+        let written = spi.take_written();
+        assert!(written.contains(&vec![EHT6.opcode(Op::BFS), 1 << 4]));
+        let hten = ReceiveFilter::HASH_TABLE.bits();
+        assert!(written.ends_with(&[vec![ERXFCON.opcode(Op::BFS), hten]]));
+    }
+}
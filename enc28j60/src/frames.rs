@@ -0,0 +1,104 @@
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi::SpiDevice;
+
+use crate::error::Error;
+use crate::spi_device::{DEFAULT_MAX_FRAME_LENGTH, Enc28j60};
+use crate::state::Ready;
+use crate::status::{ReceivedPacket, RxStatus};
+
+/// The size of the buffer held by a [`Frame`].
+const FRAME_BUFFER_LEN: usize = DEFAULT_MAX_FRAME_LENGTH as usize;
+
+/// A received frame, as yielded by [`FrameIter`].
+///
+/// The frame is held in a buffer of [`DEFAULT_MAX_FRAME_LENGTH`] bytes, and truncated if it is
+/// longer, for example after raising the limit with [`Enc28j60::set_max_frame_length`].
+#[derive(Clone, Debug)]
+pub struct Frame {
+    buf: [u8; FRAME_BUFFER_LEN],
+    packet: ReceivedPacket,
+}
+
+impl Frame {
+    /// The frame, excluding the CRC.
+    pub fn data(&self) -> &[u8] {
+        &self.buf[..self.packet.copied]
+    }
+
+    /// The length of the frame, excluding the CRC. Larger than `data().len()` if the frame was
+    /// truncated.
+    pub const fn len(&self) -> usize {
+        self.packet.total
+    }
+
+    /// Reports whether the frame is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.packet.total == 0
+    }
+
+    /// The receive status flags reported by the device for the frame.
+    pub const fn status(&self) -> RxStatus {
+        self.packet.status
+    }
+
+    /// The lengths and status of the frame, as returned by [`Enc28j60::receive`].
+    pub const fn packet(&self) -> &ReceivedPacket {
+        &self.packet
+    }
+}
+
+/// An iterator over the pending frames, returned by [`Enc28j60::frames`].
+///
+/// Frames are received until EPKTCNT reaches zero, or an error occurs. An error is yielded once,
+/// after which the iterator ends.
+pub struct FrameIter<'a, SPI: SpiDevice, INT: InputPin, RST: OutputPin, DELAY = ()> {
+    enc: &'a mut Enc28j60<SPI, INT, RST, Ready, DELAY>,
+    done: bool,
+}
+
+impl<'a, SPI, INT, RST, DELAY> FrameIter<'a, SPI, INT, RST, DELAY>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    pub(crate) fn new(enc: &'a mut Enc28j60<SPI, INT, RST, Ready, DELAY>) -> Self {
+        FrameIter { enc, done: false }
+    }
+}
+
+impl<SPI, INT, RST, DELAY> Iterator for FrameIter<'_, SPI, INT, RST, DELAY>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    type Item = Result<Frame, Error<SPI::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut buf = [0u8; FRAME_BUFFER_LEN];
+        match self.enc.receive(&mut buf) {
+            Ok(Some(packet)) => Some(Ok(Frame { buf, packet })),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<SPI, INT, RST, DELAY> core::iter::FusedIterator for FrameIter<'_, SPI, INT, RST, DELAY>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+}
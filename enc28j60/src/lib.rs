@@ -1,11 +1,36 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 #[macro_use]
 mod macros;
 
 #[cfg(feature = "simple-network")]
 mod adapter;
+#[cfg(feature = "async")]
+mod async_device;
+mod config;
+mod error;
+mod filter;
+mod frames;
+#[cfg(test)]
+mod mock;
 pub mod register;
+mod sequence;
 mod spi_device;
+mod state;
+mod status;
 
-pub use spi_device::Enc28j60;
+#[cfg(feature = "async")]
+pub use async_device::Enc28j60Async;
+pub use config::{Duplex, Enc28j60Config, InterPacketGap, LedMode, TransmitOptions};
+pub use error::{Error, InitError};
+pub use filter::{ReceiveFilter, multicast_hash_index, pattern_match_checksum};
+pub use frames::{Frame, FrameIter};
+pub use spi_device::{DEFAULT_MAC_ADDRESS, DEFAULT_MAX_FRAME_LENGTH, DEFAULT_POLL_LIMIT, Enc28j60};
+pub use state::{Ready, Uninit};
+pub use status::{
+    Diagnostics, InterruptFlags, LinkStatus, ReceivedPacket, Revision, RxStatus, Stats, TxState,
+    TxStatus,
+};
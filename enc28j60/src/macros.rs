@@ -37,3 +37,88 @@ macro_rules! phy_register {
         pub const $name: PhyRegister = PhyRegister::new($addr);
     };
 }
+
+macro_rules! register_flags {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident($ty:ty);
+        $(
+            $(#[$flag_meta:meta])*
+            const $flag:ident = $value:expr;
+        )*
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+        #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+        pub struct $name($ty);
+
+        impl $name {
+            $(
+                $(#[$flag_meta])*
+                pub const $flag: $name = $name($value);
+            )*
+
+            /// No bits set.
+            pub const fn empty() -> Self {
+                $name(0)
+            }
+
+            /// Creates a value from the raw register contents.
+            pub const fn from_bits(bits: $ty) -> Self {
+                $name(bits)
+            }
+
+            /// The raw register contents.
+            pub const fn bits(self) -> $ty {
+                self.0
+            }
+
+            /// Reports whether all bits in `other` are set.
+            pub const fn contains(self, other: $name) -> bool {
+                (self.0 & other.0) == other.0
+            }
+
+            /// Returns these bits with the bits in `other` set as well. Unlike `|`, this can be
+            /// used in constants.
+            pub const fn union(self, other: $name) -> Self {
+                $name(self.0 | other.0)
+            }
+
+            /// Sets the bits in `other`.
+            pub fn insert(&mut self, other: $name) {
+                self.0 |= other.0;
+            }
+
+            /// Clears the bits in `other`.
+            pub fn remove(&mut self, other: $name) {
+                self.0 &= !other.0;
+            }
+        }
+
+        impl From<$ty> for $name {
+            fn from(bits: $ty) -> Self {
+                $name(bits)
+            }
+        }
+
+        impl From<$name> for $ty {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl core::ops::BitOr for $name {
+            type Output = $name;
+
+            fn bitor(self, rhs: $name) -> $name {
+                $name(self.0 | rhs.0)
+            }
+        }
+
+        impl core::ops::BitOrAssign for $name {
+            fn bitor_assign(&mut self, rhs: $name) {
+                self.0 |= rhs.0;
+            }
+        }
+    };
+}
@@ -0,0 +1,207 @@
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, InputPin, OutputPin};
+use embedded_hal::spi::{self, Operation, SpiDevice};
+
+#[cfg(feature = "async")]
+use crate::Enc28j60Async;
+use crate::{DEFAULT_MAC_ADDRESS, Duplex, Enc28j60, Ready};
+
+/// A mock SPI device which records the bytes written in each transaction, and answers reads from
+/// a queue of canned responses.
+///
+/// Clones share their state, so a test keeps a clone to inspect the device owned by the driver.
+#[derive(Clone, Default)]
+pub(crate) struct MockSpi {
+    state: Rc<RefCell<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// The bytes written in each transaction, in order.
+    written: Vec<Vec<u8>>,
+    /// The bytes returned by reads. Reads past the end of the queue return 0.
+    responses: VecDeque<u8>,
+    /// Whether the next transaction fails.
+    fail_next: bool,
+}
+
+/// The error returned by a [`MockSpi`] transaction set up to fail.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct MockError;
+
+impl spi::Error for MockError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl MockSpi {
+    /// Queues `bytes` to be returned by the next reads, after the bytes queued before.
+    ///
+    /// Every byte clocked in is taken from the queue, including those received while the command
+    /// is shifted out.
+    pub(crate) fn respond(&self, bytes: &[u8]) {
+        self.state.borrow_mut().responses.extend(bytes);
+    }
+
+    /// Makes the next transaction fail with [`MockError`]. Its bytes are recorded nonetheless.
+    pub(crate) fn fail_next(&self) {
+        self.state.borrow_mut().fail_next = true;
+    }
+
+    /// Returns the bytes written in each transaction since the last call.
+    pub(crate) fn take_written(&self) -> Vec<Vec<u8>> {
+        core::mem::take(&mut self.state.borrow_mut().written)
+    }
+
+    fn fill(&self, buf: &mut [u8]) {
+        let mut state = self.state.borrow_mut();
+        for byte in buf {
+            *byte = state.responses.pop_front().unwrap_or(0);
+        }
+    }
+}
+
+impl spi::ErrorType for MockSpi {
+    type Error = MockError;
+}
+
+impl SpiDevice for MockSpi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), MockError> {
+        let mut written = Vec::new();
+        for operation in operations {
+            match operation {
+                Operation::Read(buf) => self.fill(buf),
+                Operation::Write(data) => written.extend_from_slice(data),
+                Operation::Transfer(read, write) => {
+                    written.extend_from_slice(write);
+                    self.fill(read);
+                }
+                Operation::TransferInPlace(buf) => {
+                    written.extend_from_slice(buf);
+                    self.fill(buf);
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        let mut state = self.state.borrow_mut();
+        state.written.push(written);
+        if core::mem::take(&mut state.fail_next) {
+            return Err(MockError);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::SpiDevice for MockSpi {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), MockError> {
+        SpiDevice::transaction(self, operations)
+    }
+}
+
+/// A mock pin, which reads back the level it was last set to.
+#[derive(Clone, Default)]
+pub(crate) struct MockPin {
+    high: Rc<Cell<bool>>,
+}
+
+impl digital::ErrorType for MockPin {
+    type Error = Infallible;
+}
+
+impl InputPin for MockPin {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.high.get())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.high.get())
+    }
+}
+
+impl OutputPin for MockPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.high.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.high.set(true);
+        Ok(())
+    }
+}
+
+/// A delay which returns immediately.
+pub(crate) struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Runs `future` to completion. The mocks never wait, so it completes on the first poll.
+#[cfg(feature = "async")]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = core::pin::pin!(future);
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    match future.as_mut().poll(&mut cx) {
+        core::task::Poll::Ready(output) => output,
+        core::task::Poll::Pending => panic!("mock future did not complete"),
+    }
+}
+
+/// The response to the EREVID read during initialization, identifying a B7 device.
+pub(crate) const EREVID_B7: [u8; 3] = [0, 0x06, 0];
+
+/// Returns a new driver on a mock SPI device, along with a handle to the device.
+pub(crate) fn driver() -> (Enc28j60<MockSpi, MockPin, MockPin>, MockSpi) {
+    let spi = MockSpi::default();
+    let enc = Enc28j60::new(spi.clone(), MockPin::default(), MockPin::default());
+    (enc, spi)
+}
+
+/// Returns an initialized driver on a mock SPI device, along with a handle to the device.
+///
+/// Apart from EREVID, the initialization reads back zeros. Its transactions are discarded.
+pub(crate) fn ready_driver() -> (Enc28j60<MockSpi, MockPin, MockPin, Ready>, MockSpi) {
+    let (enc, spi) = driver();
+    spi.respond(&EREVID_B7);
+    let enc = enc
+        .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+        .unwrap();
+    spi.take_written();
+    (enc, spi)
+}
+
+/// Returns a new asynchronous driver on a mock SPI device, along with a handle to the device.
+#[cfg(feature = "async")]
+pub(crate) fn async_driver() -> (Enc28j60Async<MockSpi, MockPin, MockPin>, MockSpi) {
+    let spi = MockSpi::default();
+    let enc = Enc28j60Async::new(spi.clone(), MockPin::default(), MockPin::default());
+    (enc, spi)
+}
+
+/// Like [`ready_driver`], but returns an asynchronous driver.
+#[cfg(feature = "async")]
+pub(crate) fn async_ready_driver() -> (Enc28j60Async<MockSpi, MockPin, MockPin, Ready>, MockSpi) {
+    let (enc, spi) = async_driver();
+    spi.respond(&EREVID_B7);
+    let enc = block_on(enc.initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full))
+        .map_err(|e| e.error)
+        .unwrap();
+    spi.take_written();
+    (enc, spi)
+}
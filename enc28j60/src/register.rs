@@ -1,3 +1,5 @@
+use crate::config::LedMode;
+
 #[derive(Clone, Copy)]
 pub struct ControlRegister {
     addr: u8,
@@ -5,7 +7,7 @@ pub struct ControlRegister {
     bloc: Block,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Bank {
     Bank0,
     Bank1,
@@ -88,6 +90,244 @@ impl PhyRegister {
     }
 }
 
+register_flags! {
+    /// The bits of ECON1.
+    pub struct Econ1(u8);
+
+    /// Transmit logic reset (TXRST).
+    const TXRST = 1 << 7;
+    /// Receive logic reset (RXRST).
+    const RXRST = 1 << 6;
+    /// DMA start and busy status (DMAST).
+    const DMAST = 1 << 5;
+    /// DMA checksum instead of copy (CSUMEN).
+    const CSUMEN = 1 << 4;
+    /// Transmit request to send, cleared by the device once the transmission completes (TXRTS).
+    const TXRTS = 1 << 3;
+    /// Receive enable (RXEN).
+    const RXEN = 1 << 2;
+    /// Bank select (BSEL1:BSEL0).
+    const BSEL = 0b11;
+}
+
+impl Econ1 {
+    /// The bank select bits selecting `bank`.
+    pub const fn bank_select(bank: Bank) -> Self {
+        Econ1(bank as u8)
+    }
+
+    /// The bank selected by the bank select bits.
+    pub const fn bank(self) -> Bank {
+        bank_from_u8(self.0 & Econ1::BSEL.0)
+    }
+}
+
+register_flags! {
+    /// The bits of ECON2.
+    pub struct Econ2(u8);
+
+    /// Automatic buffer pointer increment (AUTOINC).
+    const AUTOINC = 1 << 7;
+    /// Packet decrement, which decrements EPKTCNT and clears itself (PKTDEC).
+    const PKTDEC = 1 << 6;
+    /// Power save enable (PWRSV).
+    const PWRSV = 1 << 5;
+    /// Voltage regulator power save enable, which takes effect while PWRSV is set (VRPS).
+    const VRPS = 1 << 3;
+}
+
+register_flags! {
+    /// The bits of ESTAT.
+    pub struct Estat(u8);
+
+    /// INT interrupt flag, set while an enabled interrupt is pending (INT).
+    const INT = 1 << 7;
+    /// Ethernet buffer error (BUFER).
+    const BUFER = 1 << 6;
+    /// Late collision error (LATECOL).
+    const LATECOL = 1 << 4;
+    /// Receive busy (RXBUSY).
+    const RXBUSY = 1 << 2;
+    /// Transmit abort error (TXABRT).
+    const TXABRT = 1 << 1;
+    /// Clock ready (CLKRDY).
+    const CLKRDY = 1 << 0;
+}
+
+impl Estat {
+    /// The oscillator start-up timer has expired and the device is ready for use.
+    pub const fn clk_ready(self) -> bool {
+        self.contains(Estat::CLKRDY)
+    }
+
+    /// The last transmission was aborted.
+    pub const fn tx_abort(self) -> bool {
+        self.contains(Estat::TXABRT)
+    }
+
+    /// The receive logic is receiving a frame.
+    pub const fn rx_busy(self) -> bool {
+        self.contains(Estat::RXBUSY)
+    }
+
+    /// A collision occurred beyond the collision window in the last transmission.
+    pub const fn latecol(self) -> bool {
+        self.contains(Estat::LATECOL)
+    }
+
+    /// An Ethernet read or write generated a buffer error, i.e. overrun or underrun.
+    pub const fn bufer(self) -> bool {
+        self.contains(Estat::BUFER)
+    }
+}
+
+register_flags! {
+    /// The bits of EIE. The flags themselves are read from EIR as
+    /// [`InterruptFlags`](crate::InterruptFlags).
+    pub struct Eie(u8);
+
+    /// Global INT interrupt enable; no interrupt reaches the INT pin without it (INTIE).
+    const INTIE = 1 << 7;
+    /// Receive packet pending interrupt enable (PKTIE).
+    const PKTIE = 1 << 6;
+    /// DMA interrupt enable (DMAIE).
+    const DMAIE = 1 << 5;
+    /// Link status change interrupt enable (LINKIE).
+    const LINKIE = 1 << 4;
+    /// Transmit interrupt enable (TXIE).
+    const TXIE = 1 << 3;
+    /// Transmit error interrupt enable (TXERIE).
+    const TXERIE = 1 << 1;
+    /// Receive error interrupt enable (RXERIE).
+    const RXERIE = 1 << 0;
+}
+
+register_flags! {
+    /// The bits of MACON1.
+    pub struct Macon1(u8);
+
+    /// Transmit PAUSE frames for flow control, in full-duplex mode (TXPAUS).
+    const TXPAUS = 1 << 3;
+    /// Inhibit transmissions when PAUSE frames are received, in full-duplex mode (RXPAUS).
+    const RXPAUS = 1 << 2;
+    /// Pass received control frames to the receive buffer instead of discarding them (PASSALL).
+    const PASSALL = 1 << 1;
+    /// Enable the MAC to receive frames (MARXEN).
+    const MARXEN = 1 << 0;
+}
+
+register_flags! {
+    /// The bits of MACON4, which only apply in half-duplex mode.
+    pub struct Macon4(u8);
+
+    /// Wait indefinitely for the medium to become free instead of aborting the transmission after
+    /// excessive deferral, as required for conformance with IEEE 802.3 (DEFER).
+    const DEFER = 1 << 6;
+    /// Transmit immediately after backpressure collisions, without backing off (BPEN).
+    const BPEN = 1 << 5;
+    /// Transmit immediately after any collision, without backing off (NOBKOFF).
+    const NOBKOFF = 1 << 4;
+}
+
+register_flags! {
+    /// The bits of PHCON1.
+    pub struct PhCon1(u16);
+
+    /// Reset the PHY (PRST).
+    const PRST = 1 << 15;
+    /// Loop transmitted frames back to the MAC inside the PHY (PLOOPBK).
+    const PLOOPBK = 1 << 14;
+    /// Power down the PHY (PPWRSV).
+    const PPWRSV = 1 << 11;
+    /// Operate the PHY in full-duplex mode, which must match MACON3.FULDPX (PDPXMD).
+    const PDPXMD = 1 << 8;
+}
+
+register_flags! {
+    /// The bits of PHCON2.
+    pub struct PhCon2(u16);
+
+    /// Force the link up, even without a link partner (FRCLNK).
+    const FRCLNK = 1 << 14;
+    /// Disable the twisted-pair transmitter (TXDIS).
+    const TXDIS = 1 << 13;
+    /// Disable jabber correction (JABBER).
+    const JABBER = 1 << 10;
+    /// In half-duplex mode, do not loop transmitted frames back to the MAC (HDLDIS).
+    const HDLDIS = 1 << 8;
+}
+
+register_flags! {
+    /// The bits of PHSTAT1.
+    pub struct PhStat1(u16);
+
+    /// The PHY can operate in full-duplex mode (PFDPX).
+    const PFDPX = 1 << 12;
+    /// The PHY can operate in half-duplex mode (PHDPX).
+    const PHDPX = 1 << 11;
+    /// The link has been up since PHSTAT1 was last read. Clears when the link goes down and
+    /// stays clear until PHSTAT1 is read (LLSTAT).
+    const LLSTAT = 1 << 2;
+    /// Jabber has been detected since PHSTAT1 was last read (JBSTAT).
+    const JBSTAT = 1 << 1;
+}
+
+register_flags! {
+    /// The bits of PHSTAT2.
+    pub struct PhStat2(u16);
+
+    /// The PHY is transmitting (TXSTAT).
+    const TXSTAT = 1 << 13;
+    /// The PHY is receiving (RXSTAT).
+    const RXSTAT = 1 << 12;
+    /// A collision is occurring (COLSTAT).
+    const COLSTAT = 1 << 11;
+    /// The link is up (LSTAT).
+    const LSTAT = 1 << 10;
+    /// The PHY operates in full-duplex mode (DPXSTAT).
+    const DPXSTAT = 1 << 9;
+    /// The polarity of the received signal is reversed (PLRITY).
+    const PLRITY = 1 << 5;
+}
+
+register_flags! {
+    /// The bits of PHIE.
+    pub struct Phie(u16);
+
+    /// Raise a PHY interrupt when the link changes (PLNKIE).
+    const PLNKIE = 1 << 4;
+    /// Forward enabled PHY interrupts to the MAC as EIR.LINKIF (PGEIE).
+    const PGEIE = 1 << 1;
+}
+
+register_flags! {
+    /// The bits of PHIR, which clear when PHIR is read.
+    pub struct Phir(u16);
+
+    /// The link changed since PHIR was last read (PLNKIF).
+    const PLNKIF = 1 << 4;
+    /// A PHY interrupt is pending (PGIF).
+    const PGIF = 1 << 2;
+}
+
+register_flags! {
+    /// The bits of PHLCON. The LED functions are set with [`PhLcon::with_leds`].
+    pub struct PhLcon(u16);
+
+    /// Bits 13:12, which are reserved and must be written as 1.
+    const RESERVED = 0b11 << 12;
+    /// Stretch LED events to the duration set by LFRQ, which is about 40 ms when clear (STRCH).
+    const STRCH = 1 << 1;
+}
+
+impl PhLcon {
+    /// Returns these bits with LEDA and LEDB configured for the given functions (LACFG, LBCFG).
+    pub const fn with_leds(self, leda: LedMode, ledb: LedMode) -> Self {
+        const LED_MASK: u16 = 0x0ff0;
+        PhLcon((self.0 & !LED_MASK) | ((leda as u16) << 8) | ((ledb as u16) << 4))
+    }
+}
+
 const fn bank_from_u8(bank: u8) -> Bank {
     match bank {
         0 => Bank::Bank0,
@@ -128,10 +368,38 @@ control_registers![
     (ERXRDPTH, 0x0d, 0, Eth),
     (ERXWRPTL, 0x0e, 0, Eth),
     (ERXWRPTH, 0x0f, 0, Eth),
+    (EDMASTL,  0x10, 0, Eth),
+    (EDMASTH,  0x11, 0, Eth),
+    (EDMANDL,  0x12, 0, Eth),
+    (EDMANDH,  0x13, 0, Eth),
+    (EDMADSTL, 0x14, 0, Eth),
+    (EDMADSTH, 0x15, 0, Eth),
+    (EDMACSL,  0x16, 0, Eth),
+    (EDMACSH,  0x17, 0, Eth),
 
     //
     // Bank 1 registers
     //
+    (EHT0,    0x00, 1, Eth),
+    (EHT1,    0x01, 1, Eth),
+    (EHT2,    0x02, 1, Eth),
+    (EHT3,    0x03, 1, Eth),
+    (EHT4,    0x04, 1, Eth),
+    (EHT5,    0x05, 1, Eth),
+    (EHT6,    0x06, 1, Eth),
+    (EHT7,    0x07, 1, Eth),
+    (EPMM0,   0x08, 1, Eth),
+    (EPMM1,   0x09, 1, Eth),
+    (EPMM2,   0x0a, 1, Eth),
+    (EPMM3,   0x0b, 1, Eth),
+    (EPMM4,   0x0c, 1, Eth),
+    (EPMM5,   0x0d, 1, Eth),
+    (EPMM6,   0x0e, 1, Eth),
+    (EPMM7,   0x0f, 1, Eth),
+    (EPMCSL,  0x10, 1, Eth),
+    (EPMCSH,  0x11, 1, Eth),
+    (EPMOL,   0x14, 1, Eth),
+    (EPMOH,   0x15, 1, Eth),
     (ERXFCON, 0x18, 1, Eth),
     (EPKTCNT, 0x19, 1, Eth),
 
@@ -156,14 +424,17 @@ control_registers![
     //
     // Bank 3 registers
     //
-    (MAADR5, 0x00, 3, Mac),
-    (MAADR6, 0x01, 3, Mac),
-    (MAADR3, 0x02, 3, Mac),
-    (MAADR4, 0x03, 3, Mac),
-    (MAADR1, 0x04, 3, Mac),
-    (MAADR2, 0x05, 3, Mac),
-    (MISTAT, 0x0a, 3, Mii),
-    (EREVID, 0x12, 3, Eth),
+    (MAADR5,  0x00, 3, Mac),
+    (MAADR6,  0x01, 3, Mac),
+    (MAADR3,  0x02, 3, Mac),
+    (MAADR4,  0x03, 3, Mac),
+    (MAADR1,  0x04, 3, Mac),
+    (MAADR2,  0x05, 3, Mac),
+    (MISTAT,  0x0a, 3, Mii),
+    (EREVID,  0x12, 3, Eth),
+    (EFLOCON, 0x17, 3, Eth),
+    (EPAUSL,  0x18, 3, Eth),
+    (EPAUSH,  0x19, 3, Eth),
 ];
 
 #[rustfmt::skip]
@@ -0,0 +1,291 @@
+//! Register sequences shared by the blocking and the asynchronous driver.
+//!
+//! The sequences are built as lists of [`Step`]s, which each driver runs with its own register
+//! access. What to do between register accesses is decided by the pure functions and state
+//! machines in this module, so that both drivers issue the same commands in the same order.
+
+use crate::config::{ConfigRegisters, Enc28j60Config, TransmitOptions};
+use crate::error::Error;
+use crate::register::*;
+use crate::spi_device::{BUFFER_END, MAADR, RX_END, RX_START, TX_START, receive_read_pointer};
+use crate::status::{InterruptFlags, Revision, TxState, TxStatus};
+
+/// A single register access of a sequence.
+#[derive(Clone, Copy)]
+pub(crate) enum Step {
+    /// Writes a control register (WCR).
+    Write(ControlRegister, u8),
+    /// Sets bits of an ETH register (BFS).
+    Set(ControlRegister, u8),
+    /// Clears bits of an ETH register (BFC).
+    Clear(ControlRegister, u8),
+    /// Writes a PHY register through MIREGADR and MIWRL/MIWRH.
+    WritePhy(PhyRegister, u16),
+}
+
+/// Writes `val` into the register pair `lo` and `hi`, low byte first.
+pub(crate) fn write_u16(lo: ControlRegister, hi: ControlRegister, val: u16) -> [Step; 2] {
+    let [val_lo, val_hi] = val.to_le_bytes();
+    [Step::Write(lo, val_lo), Step::Write(hi, val_hi)]
+}
+
+/// Checks the EREVID value read back after a reset.
+///
+/// Fails with [`Error::DeviceNotResponding`] for 00h and FFh, which usually mean a wiring or power
+/// problem, and with [`Error::UnsupportedRevision`] for a revision not listed in the errata.
+pub(crate) fn check_revision<E>(erevid: u8) -> Result<(), Error<E>> {
+    match Revision::from_erevid(erevid) {
+        Revision::B1 | Revision::B4 | Revision::B5 | Revision::B7 => Ok(()),
+        Revision::Unknown(0x00 | 0xff) => Err(Error::DeviceNotResponding),
+        Revision::Unknown(erevid) => Err(Error::UnsupportedRevision(erevid)),
+    }
+}
+
+/// The steps which configure a device that was just reset according to `config`, whose register
+/// values are `registers`, and then enable reception.
+pub(crate) fn configure(
+    config: &Enc28j60Config,
+    registers: &ConfigRegisters,
+) -> impl Iterator<Item = Step> {
+    // MAIPGH and MACON4 are only used in half-duplex mode.
+    let half_duplex = registers
+        .half_duplex
+        .into_iter()
+        .flat_map(|(maipgh, macon4)| {
+            [
+                Step::Write(MAIPGH, maipgh),
+                Step::Write(MACON4, macon4.bits()),
+            ]
+        });
+    let mac_address = MAADR
+        .into_iter()
+        .zip(config.mac_address)
+        .map(|(reg, octet)| Step::Write(reg, octet));
+
+    // Receiving and transmitting rely on ERDPT and EWRPT advancing with every byte.
+    [Step::Set(ECON2, Econ2::AUTOINC.bits())]
+        .into_iter()
+        // Before receiving any packets, the receive buffer must be initialized by programming the
+        // ERXST and ERXND Pointers. For tracking purposes, the ERXRDPT registers should
+        // additionally be programmed with the same value.
+        .chain(write_u16(ERXSTL, ERXSTH, RX_START))
+        .chain(write_u16(ERXNDL, ERXNDH, RX_END))
+        .chain(write_u16(ERXRDPTL, ERXRDPTH, RX_START))
+        // No explicit action is required to initialize the transmission buffer.
+        .chain(write_u16(ETXSTL, ETXSTH, TX_START))
+        // Set the MARXEN bit in MACON1 to enable the MAC to receive frames, and configure the
+        // PADCFG, TXCRCEN and FULDPX bits of MACON3.
+        .chain([
+            Step::Write(MACON1, Macon1::MARXEN.bits()),
+            Step::Write(MACON3, registers.macon3),
+        ])
+        // Program the MAMXFL registers with the maximum frame length.
+        .chain(write_u16(MAMXFLL, MAMXFLH, registers.max_frame_length))
+        // Configure MABBIPG with the back-to-back inter-packet gap, and MAIPGL with the
+        // non-back-to-back inter-packet gap.
+        .chain([
+            Step::Write(MABBIPG, registers.back_to_back_gap),
+            Step::Write(MAIPGL, registers.non_back_to_back_gap),
+        ])
+        .chain(half_duplex)
+        // Program the local MAC address. Reception is still disabled after the reset.
+        .chain(mac_address)
+        .chain([
+            Step::Write(ERXFCON, config.receive_filter.bits()),
+            // For proper duplex operation, PHCON1.PDPXMD must also match MACON3.FULDPX.
+            Step::WritePhy(PHCON1, registers.phcon1.bits()),
+            // Set PHCON2.HDLDIS so that, in half-duplex mode, transmitted frames are not looped
+            // back to the MAC.
+            Step::WritePhy(PHCON2, registers.phcon2.bits()),
+            // Issue interrupts when packets arrive. This allows users to wfi() in a loop to
+            // efficiently wait for incoming packets.
+            Step::Write(EIE, (Eie::INTIE | Eie::PKTIE).bits()),
+            // The receive buffer, the MAC and the receive filter are set up, so enable reception.
+            Step::Write(ECON1, Econ1::RXEN.bits()),
+        ])
+}
+
+/// The steps which disable reception and reset the receive logic. The reset leaves EPKTCNT as it
+/// is, so [`decrement_packet_count`] must be run for each pending packet before
+/// [`restart_receive`].
+pub(crate) fn reset_receive() -> [Step; 3] {
+    [
+        Step::Clear(ECON1, Econ1::RXEN.bits()),
+        Step::Set(ECON1, Econ1::RXRST.bits()),
+        Step::Clear(ECON1, Econ1::RXRST.bits()),
+    ]
+}
+
+/// The step which decrements EPKTCNT by setting ECON2.PKTDEC, once a packet has been released.
+pub(crate) fn decrement_packet_count() -> Step {
+    Step::Set(ECON2, Econ2::PKTDEC.bits())
+}
+
+/// The steps which empty the receive buffer starting at `rx_start` and enable reception again,
+/// after [`reset_receive`]. The next packet is then expected at `rx_start`.
+pub(crate) fn restart_receive(rx_start: u16) -> [Step; 5] {
+    // Writing ERXST also moves the hardware write pointer back to the start of the buffer.
+    let [erxstl, erxsth] = write_u16(ERXSTL, ERXSTH, rx_start);
+    let [erxrdptl, erxrdpth] = write_u16(ERXRDPTL, ERXRDPTH, rx_start);
+    [
+        erxstl,
+        erxsth,
+        erxrdptl,
+        erxrdpth,
+        Step::Set(ECON1, Econ1::RXEN.bits()),
+    ]
+}
+
+/// The steps which free the memory of the packet before `next_packet` in the receive buffer
+/// spanning `rx_start..=rx_end`, and decrement EPKTCNT.
+pub(crate) fn release_packet(next_packet: u16, rx_start: u16, rx_end: u16) -> [Step; 3] {
+    // ERXRDPT should point to the byte before the next packet's start.
+    let rdpt = receive_read_pointer(next_packet, rx_start, rx_end);
+    let [erxrdptl, erxrdpth] = write_u16(ERXRDPTL, ERXRDPTH, rdpt);
+    [erxrdptl, erxrdpth, decrement_packet_count()]
+}
+
+/// Checks that a frame of `frame_len` bytes, from the destination address to the end of the
+/// payload, may be transmitted with `options` under the maximum frame length `max_frame_len`.
+pub(crate) fn check_frame_length<E>(
+    frame_len: usize,
+    max_frame_len: u16,
+    options: &TransmitOptions,
+) -> Result<(), Error<E>> {
+    // The MAC appends the 4-byte CRC, which counts towards the maximum frame length. Without it,
+    // the payload is expected to end with the FCS instead.
+    let overridden = options.override_defaults;
+    let crc_len = if overridden && !options.append_crc {
+        0
+    } else {
+        4
+    };
+    if frame_len + crc_len > usize::from(max_frame_len) && !(overridden && options.allow_huge) {
+        return Err(Error::FrameTooLong);
+    }
+    Ok(())
+}
+
+/// Returns the ETXND value for a frame of `frame_len` bytes, written behind its per-packet control
+/// byte at `tx_start`.
+///
+/// Fails with [`Error::FrameTooLong`] unless the control byte, the frame and the transmit status
+/// vector all fit between `tx_start` and the end of the buffer memory.
+pub(crate) fn transmit_end<E>(tx_start: u16, frame_len: usize) -> Result<u16, Error<E>> {
+    let tx_space = usize::from(BUFFER_END - tx_start.min(BUFFER_END)) + 1;
+    if 1 + frame_len + TxStatus::LEN > tx_space {
+        return Err(Error::FrameTooLong);
+    }
+    // ETXND points to the last byte of the frame.
+    Ok(tx_start + frame_len as u16)
+}
+
+/// The steps which pulse ECON1.TXRST and clear the transmit interrupt flags.
+pub(crate) fn reset_transmit() -> [Step; 3] {
+    let flags = InterruptFlags::TX_ERROR | InterruptFlags::TRANSMIT;
+    [
+        Step::Set(ECON1, Econ1::TXRST.bits()),
+        Step::Clear(ECON1, Econ1::TXRST.bits()),
+        Step::Clear(EIR, flags.clear_mask()),
+    ]
+}
+
+/// The steps which start transmitting the frame between ETXST and ETXND.
+pub(crate) fn arm_transmit() -> [Step; 2] {
+    // Clear EIR.TXIF, and EIR.TXERIF in case a previous transmission failed. For now, we do not
+    // enable interrupts (EIE.TXIE and EIE.INTIE).
+    let flags = InterruptFlags::TRANSMIT | InterruptFlags::TX_ERROR;
+    [
+        Step::Clear(EIR, flags.clear_mask()),
+        // Start the transmission process by setting ECON1.TXRTS.
+        Step::Set(ECON1, Econ1::TXRTS.bits()),
+    ]
+}
+
+/// Decides how a transmission ended, once ECON1.TXRTS has cleared, from ESTAT and the transmit
+/// status vector.
+///
+/// The transmission counts as aborted if ESTAT.TXABRT is set, or if the status vector reports a
+/// late or excessive collision. [`clear_transmit_abort`] must then be run.
+pub(crate) fn transmit_state(estat: Estat, status: TxStatus) -> TxState {
+    if estat.tx_abort() || status.late_collision() || status.excessive_collision() {
+        TxState::Aborted(status)
+    } else {
+        TxState::Done(status)
+    }
+}
+
+/// The steps which clear ESTAT.TXABRT, ESTAT.LATECOL and EIR.TXERIF after an aborted
+/// transmission.
+pub(crate) fn clear_transmit_abort() -> [Step; 2] {
+    [
+        Step::Clear(ESTAT, (Estat::TXABRT | Estat::LATECOL).bits()),
+        Step::Clear(EIR, InterruptFlags::TX_ERROR.clear_mask()),
+    ]
+}
+
+/// What to do next while waiting for a transmission to complete.
+pub(crate) enum TransmitAction<E> {
+    /// Poll the transmission again.
+    Poll,
+    /// Run [`reset_transmit`], since the transmit logic may stall after an abort (errata), then
+    /// [`arm_transmit`] to retry the frame still in the buffer.
+    Retry,
+    /// Stop with this result.
+    Finish(Result<TxStatus, Error<E>>),
+}
+
+/// Waits for a transmission to complete, retrying it when it is aborted.
+pub(crate) struct TransmitRetry {
+    /// Number of retries left,
+    retries: u8,
+    /// Number of polls of the current attempt,
+    polls: u32,
+    /// Maximum number of polls of each attempt,
+    poll_limit: u32,
+}
+
+impl TransmitRetry {
+    /// Retries an aborted transmission up to `retries` times, and gives up on an attempt with
+    /// [`Error::Timeout`] after `poll_limit` polls.
+    pub(crate) fn new(retries: u8, poll_limit: u32) -> Self {
+        TransmitRetry {
+            retries,
+            polls: 0,
+            poll_limit,
+        }
+    }
+
+    /// Decides what to do after a poll of the transmission reported `state`.
+    pub(crate) fn next<E>(&mut self, state: TxState) -> TransmitAction<E> {
+        match state {
+            TxState::InProgress => {
+                self.polls += 1;
+                if self.polls >= self.poll_limit {
+                    return TransmitAction::Finish(Err(Error::Timeout));
+                }
+                TransmitAction::Poll
+            }
+            TxState::Done(status) => TransmitAction::Finish(Ok(status)),
+            TxState::Aborted(status) if self.retries == 0 => {
+                TransmitAction::Finish(Err(abort_error(status)))
+            }
+            TxState::Aborted(_) => {
+                self.retries -= 1;
+                self.polls = 0;
+                TransmitAction::Retry
+            }
+        }
+    }
+}
+
+/// The error reported when the last attempt at a transmission was aborted with `status`.
+fn abort_error<E>(status: TxStatus) -> Error<E> {
+    if status.late_collision() {
+        Error::LateCollision
+    } else if status.excessive_collision() {
+        Error::ExcessiveCollisions
+    } else {
+        Error::Aborted
+    }
+}
@@ -1,162 +1,549 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
 use core::cmp::min;
+use core::marker::PhantomData;
+use core::ops::RangeInclusive;
 
 use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal::spi::{Operation, SpiDevice};
 
+use super::config::{Duplex, Enc28j60Config, InterPacketGap, LedMode, TransmitOptions};
+use super::error::{Error, InitError};
+use super::filter::{ReceiveFilter, multicast_hash_index};
+use super::frames::FrameIter;
 use super::register::*;
-
-pub struct Enc28j60<SPI: SpiDevice, INT: InputPin, RST: OutputPin> {
+use super::sequence::{self, Step, TransmitAction, TransmitRetry};
+use super::state::{Ready, Uninit};
+use super::status::{
+    Diagnostics, InterruptFlags, LinkStatus, ReceivedPacket, Revision, RxStatus, Stats, TxState,
+    TxStatus,
+};
+
+/// A placeholder station MAC address for callers that do not yet have their own.
+///
+/// Production devices should use an address derived from a unique source, such as the MCU's
+/// unique ID or a factory-programmed EEPROM.
+pub const DEFAULT_MAC_ADDRESS: [u8; 6] = [0xff, 0xca, 0xde, 0xee, 0xff, 0xc0];
+
+/// The default number of register reads to poll for before giving up with [`Error::Timeout`].
+pub const DEFAULT_POLL_LIMIT: u32 = 100_000;
+
+/// The default maximum length of a frame, including the CRC.
+pub const DEFAULT_MAX_FRAME_LENGTH: u16 = 1518;
+
+/// The range of maximum frame lengths accepted by [`Enc28j60::set_max_frame_length`]. A received
+/// frame and its 6-byte receive status vector must fit in the 4 KiB receive buffer.
+pub(crate) const MAX_FRAME_LENGTH_RANGE: RangeInclusive<u16> = 64..=0x1000 - 6;
+
+/// The last address of the 8 KiB buffer memory.
+pub(crate) const BUFFER_END: u16 = 0x1fff;
+
+/// The start of the receive buffer, which must be 0000h because of silicon errata issue 5.
+pub(crate) const RX_START: u16 = 0x0000;
+
+/// The end of the receive buffer, as programmed into ERXND.
+pub(crate) const RX_END: u16 = TX_START - 1;
+
+/// The start of the transmit buffer, as programmed into ETXST.
+///
+/// It is recommended that ETXST points to an unused location in memory, and that its address is
+/// even.
+pub(crate) const TX_START: u16 = 0x1000;
+
+/// The System Reset Command (SRC). Unlike other SPI commands, it is a single byte and does not
+/// operate on any register.
+pub(crate) const SRC_COMMAND: u8 = 0xff;
+
+/// The Read Buffer Memory command (RBM), which reads from ERDPT.
+pub(crate) const RBM_COMMAND: u8 = (Op::RBM as u8) | 0x1a;
+
+/// The Write Buffer Memory command (WBM), which writes from EWRPT.
+pub(crate) const WBM_COMMAND: u8 = (Op::WBM as u8) | 0x1a;
+
+/// The panic message for a missing stored delay. Only [`Enc28j60::new`] leaves the delay out,
+/// and its `()` delay type does not implement [`DelayNs`], so this cannot happen.
+const STORED_DELAY: &str = "driver created without a delay";
+
+/// The hash table registers, from the least significant byte of the table to the most.
+const EHT: [ControlRegister; 8] = [EHT0, EHT1, EHT2, EHT3, EHT4, EHT5, EHT6, EHT7];
+
+/// The pattern match mask registers, from the least significant byte of the mask to the most.
+const EPMM: [ControlRegister; 8] = [EPMM0, EPMM1, EPMM2, EPMM3, EPMM4, EPMM5, EPMM6, EPMM7];
+
+/// The station MAC address registers, in wire order.
+///
+/// MAADR1 holds the first octet on the wire, even though the MAADR registers are laid out in
+/// reverse order within bank 3.
+pub(crate) const MAADR: [ControlRegister; 6] = [MAADR1, MAADR2, MAADR3, MAADR4, MAADR5, MAADR6];
+
+/// A driver for an ENC28J60 on an SPI bus.
+///
+/// The driver starts out in the [`Uninit`] state, where only register access is available.
+/// [`Enc28j60::initialize`] turns it into a [`Ready`] driver, which can also receive and transmit
+/// frames and configure the filters.
+pub struct Enc28j60<SPI: SpiDevice, INT: InputPin, RST: OutputPin, S = Uninit, DELAY = ()> {
     /// An SPI device
     spi: SPI,
 
-    #[allow(dead_code)]
     /// Interrupt pin
     int: INT,
 
     /// Reset pin
     reset: RST,
 
-    /// Current bank,
-    current_bank: Bank,
+    /// Current bank, or `None` if unknown after a failed transfer.
+    current_bank: Option<Bank>,
 
     /// Next packet pointer,
     next_packet: u16,
+
+    /// Start of the receive buffer, as programmed into ERXST,
+    rx_start: u16,
+
+    /// End of the receive buffer, as programmed into ERXND,
+    rx_end: u16,
+
+    /// Maximum number of register reads while polling for the device,
+    poll_limit: u32,
+
+    /// Whether to reset the transmit logic before each transmission,
+    reset_tx_logic: bool,
+
+    /// Number of times to retry an aborted transmission,
+    tx_retries: u8,
+
+    /// Maximum length of a frame, including the CRC,
+    max_frame_len: u16,
+
+    /// Inter-packet gaps, or `None` for the recommended ones,
+    inter_packet_gap: Option<InterPacketGap>,
+
+    /// Half-duplex deferral and backoff settings,
+    macon4: Macon4,
+
+    /// Station MAC address,
+    mac_address: [u8; 6],
+
+    /// Duplex mode,
+    duplex: Duplex,
+
+    /// Receive filters last programmed into ERXFCON,
+    receive_filter: ReceiveFilter,
+
+    /// Hash table last programmed into EHT0-EHT7, with EHT0 in the least significant byte,
+    hash_table: u64,
+
+    /// Pattern match filter last programmed with [`Enc28j60::set_pattern_match`],
+    pattern_match: Option<PatternMatch>,
+
+    /// Whether a transmission was started and its completion not yet reported,
+    tx_pending: bool,
+
+    /// Frame counters,
+    stats: Stats,
+
+    /// Whether the device has been initialized since it was last reset. A [`Ready`] driver stays
+    /// [`Ready`] across [`Enc28j60::reset`] and [`Enc28j60::reset_via_spi`], so receiving and
+    /// transmitting still check this,
+    initialized: bool,
+
+    /// Delay owned by the driver, if it was created with [`Enc28j60::new_with_delay`],
+    delay: Option<DELAY>,
+
+    /// Initialization state,
+    state: PhantomData<S>,
 }
 
-impl<SPI, INT, RST> Enc28j60<SPI, INT, RST>
+impl<SPI, INT, RST> Enc28j60<SPI, INT, RST, Uninit>
 where
     SPI: SpiDevice,
     INT: InputPin,
     RST: OutputPin,
 {
     pub fn new(spi: SPI, int: INT, reset: RST) -> Self {
+        Enc28j60::with_delay(spi, int, reset, None)
+    }
+}
+
+impl<SPI, INT, RST, DELAY> Enc28j60<SPI, INT, RST, Uninit, DELAY>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    /// Creates a driver which owns `delay`, so that [`Enc28j60::reset_with_stored_delay`],
+    /// [`Enc28j60::initialize_with_stored_delay`] and [`Enc28j60::recover_with_stored_delay`]
+    /// need no delay argument. The methods taking a delay remain available.
+    ///
+    /// The driver holds on to the delay until it is dropped, which suits a dedicated timer. A
+    /// delay shared with other code, such as the SysTick, is better lent to each call of the
+    /// methods taking one, after creating the driver with [`Enc28j60::new`].
+    pub fn new_with_delay(spi: SPI, int: INT, reset: RST, delay: DELAY) -> Self
+    where
+        DELAY: DelayNs,
+    {
+        Enc28j60::with_delay(spi, int, reset, Some(delay))
+    }
+
+    fn with_delay(spi: SPI, int: INT, reset: RST, delay: Option<DELAY>) -> Self {
         Enc28j60 {
             spi,
             int,
             reset,
-            current_bank: Bank::Bank0,
+            current_bank: Some(Bank::Bank0),
             next_packet: 0,
+            rx_start: 0,
+            rx_end: 0,
+            poll_limit: DEFAULT_POLL_LIMIT,
+            reset_tx_logic: true,
+            tx_retries: 0,
+            max_frame_len: DEFAULT_MAX_FRAME_LENGTH,
+            inter_packet_gap: None,
+            macon4: Macon4::empty(),
+            mac_address: DEFAULT_MAC_ADDRESS,
+            duplex: Duplex::Full,
+            receive_filter: ReceiveFilter::empty(),
+            hash_table: 0,
+            pattern_match: None,
+            tx_pending: false,
+            stats: Stats::default(),
+            initialized: false,
+            delay,
+            state: PhantomData,
         }
     }
 
-    pub fn initialize<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), SPI::Error> {
-        self.reset_via_spi(delay)?;
+    /// Sets the maximum length of a frame, including the CRC, which `initialize` programs into
+    /// MAMXFL. Defaults to [`DEFAULT_MAX_FRAME_LENGTH`].
+    ///
+    /// The MAC rejects received frames longer than this, and [`Enc28j60::transmit`] refuses to
+    /// send them. Use 1522 to make room for an 802.1Q tag. Fails with [`Error::InvalidArgument`]
+    /// unless the length is between 64 bytes and the size of the 4 KiB receive buffer less the
+    /// 6-byte receive status vector.
+    pub fn set_max_frame_length(&mut self, len: u16) -> Result<(), Error<SPI::Error>> {
+        if !MAX_FRAME_LENGTH_RANGE.contains(&len) {
+            return Err(Error::InvalidArgument);
+        }
+        self.max_frame_len = len;
+        Ok(())
+    }
 
-        let revision = self.read_control(EREVID).unwrap_or(0xff);
+    /// Sets the inter-packet gaps which `initialize` programs, overriding those recommended for
+    /// the duplex mode by [`InterPacketGap::recommended`]. Longer gaps can help with link
+    /// partners that drop back-to-back frames.
+    pub fn set_inter_packet_gap(&mut self, gap: InterPacketGap) {
+        self.inter_packet_gap = Some(gap);
+    }
+}
 
-        match revision {
-            0x00 | 0xff => { /* Chip reset, or read failure */ }
-            0b0010 | 0b1000 | 0b0101 | 0b0110 => { /* Hardware bug */ }
-            _ => loop {
-                let estat = self.read_control(ESTAT)?;
-                if (estat & 0x01) != 0 {
-                    break;
-                }
-            },
+impl<SPI, INT, RST, S, DELAY> Enc28j60<SPI, INT, RST, S, DELAY>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    /// Sets the maximum number of register reads while polling the device for completion of an
+    /// operation. Operations that exceed it fail with [`Error::Timeout`] instead of hanging.
+    pub fn set_poll_limit(&mut self, limit: u32) {
+        self.poll_limit = limit;
+    }
+
+    /// Sets whether the transmit logic is reset before each transmission. Enabled by default.
+    ///
+    /// Silicon errata issue 12: in half-duplex mode, a transmit abort caused by excessive
+    /// collisions, a late collision or excessive deferrals may stall the internal transmit logic,
+    /// so that ECON1.TXRTS never clears on the next transmission. The workaround is to pulse
+    /// ECON1.TXRST before setting ECON1.TXRTS. This costs three extra SPI writes per transmission,
+    /// and may be disabled on full-duplex links where aborts cannot occur.
+    pub fn set_transmit_reset(&mut self, enabled: bool) {
+        self.reset_tx_logic = enabled;
+    }
+
+    /// Sets how many times an aborted transmission is retried before [`Error::Aborted`] is
+    /// returned. Defaults to 0.
+    ///
+    /// Aborts are caused by excessive collisions, late collisions or excessive deferrals, and can
+    /// therefore only happen in half-duplex mode.
+    pub fn set_transmit_retries(&mut self, retries: u8) {
+        self.tx_retries = retries;
+    }
+
+    /// Returns the maximum length of a frame, including the CRC, as set with
+    /// [`Enc28j60::set_max_frame_length`] or [`Enc28j60Config::max_frame_length`].
+    pub fn max_frame_length(&self) -> u16 {
+        self.max_frame_len
+    }
+
+    /// Returns the frame counters, which are updated as frames are received and transmitted.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Resets the frame counters to zero.
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// Consumes the driver and returns the SPI device, the interrupt pin and the reset pin, so
+    /// they can be reused.
+    ///
+    /// The device is left as it is. Hold it in reset, or reset it before handing the bus to
+    /// another driver, if it should stop receiving.
+    pub fn into_inner(self) -> (SPI, INT, RST) {
+        (self.spi, self.int, self.reset)
+    }
+
+    /// An alias for [`Enc28j60::into_inner`].
+    pub fn free(self) -> (SPI, INT, RST) {
+        self.into_inner()
+    }
+
+    /// Returns the SPI device, for transactions the driver does not provide.
+    ///
+    /// The driver caches the selected register bank, and relies on the read and write pointers
+    /// it programmed. Changing ECON1.BSEL0/BSEL1 through the returned device desynchronizes the
+    /// cache, so that later register accesses hit the wrong bank; call
+    /// [`Enc28j60::resync_bank`] or [`Enc28j60::invalidate_bank_cache`] afterwards. Moving
+    /// ERDPT, EWRPT or the receive pointers while a frame is being received or transmitted
+    /// corrupts it.
+    pub fn spi_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// Reads ECON1 to update the cached register bank, after the bank was selected behind the
+    /// driver's back, for example through [`Enc28j60::spi_mut`].
+    pub fn resync_bank(&mut self) -> Result<Bank, Error<SPI::Error>> {
+        let bank = self.read_reg::<Econ1>(ECON1)?.bank();
+        self.current_bank = Some(bank);
+        Ok(bank)
+    }
+
+    /// Forgets the cached register bank, so that the next banked register access selects its bank
+    /// with both bank select bits. Unlike [`Enc28j60::resync_bank`], this takes no SPI transfer.
+    ///
+    /// The driver already does this when a register access fails, since the bank selected by a
+    /// glitched transfer is unknown.
+    pub fn invalidate_bank_cache(&mut self) {
+        self.current_bank = None;
+    }
+
+    /// Resets and configures the device, programming `mac` as the station MAC address and setting
+    /// the MAC and PHY to the given `duplex` mode.
+    ///
+    /// The other settings are those made with the setters, which default to
+    /// [`Enc28j60Config::default`], and every frame is accepted. See
+    /// [`Enc28j60::initialize_with`] for the details.
+    #[allow(clippy::type_complexity)]
+    pub fn initialize<D: DelayNs>(
+        self,
+        delay: &mut D,
+        mac: &[u8; 6],
+        duplex: Duplex,
+    ) -> Result<
+        Enc28j60<SPI, INT, RST, Ready, DELAY>,
+        InitError<Enc28j60<SPI, INT, RST, Uninit, DELAY>, SPI::Error>,
+    > {
+        let config = Enc28j60Config {
+            mac_address: *mac,
+            duplex,
+            receive_filter: ReceiveFilter::empty(),
+            ..self.config()
+        };
+        self.initialize_with(delay, &config)
+    }
+
+    /// Like [`Enc28j60::initialize`], but waits with the delay passed to
+    /// [`Enc28j60::new_with_delay`].
+    #[allow(clippy::type_complexity)]
+    pub fn initialize_with_stored_delay(
+        mut self,
+        mac: &[u8; 6],
+        duplex: Duplex,
+    ) -> Result<
+        Enc28j60<SPI, INT, RST, Ready, DELAY>,
+        InitError<Enc28j60<SPI, INT, RST, Uninit, DELAY>, SPI::Error>,
+    >
+    where
+        DELAY: DelayNs,
+    {
+        let mut delay = self.delay.take().expect(STORED_DELAY);
+        match self.initialize(&mut delay, mac, duplex) {
+            Ok(mut enc) => {
+                enc.delay = Some(delay);
+                Ok(enc)
+            }
+            Err(mut error) => {
+                error.driver.delay = Some(delay);
+                Err(error)
+            }
         }
+    }
 
-        self.ensure_autoinc()?;
+    /// Runs `f` with the delay passed to [`Enc28j60::new_with_delay`], which is taken out of the
+    /// driver meanwhile.
+    fn with_stored_delay<R>(&mut self, f: impl FnOnce(&mut Self, &mut DELAY) -> R) -> R
+    where
+        DELAY: DelayNs,
+    {
+        let mut delay = self.delay.take().expect(STORED_DELAY);
+        let result = f(self, &mut delay);
+        self.delay = Some(delay);
+        result
+    }
 
-        //
-        // Set up receive and transmit buffers
-        //
-        {
-            const RX_START: u16 = 0x0000;
-            const RX_END: u16 = TX_START - 1;
-            // It is recommended that:
-            // 1. ETXST points to an unused location in memory.
-            // 2. the address of ETXST is even.
-            const TX_START: u16 = 0x1000;
-
-            // Before receiving any packets, the receive buffer must be initialized by programming
-            // the ERXST and ERXND Pointers.
-            self.write_u16(ERXSTL, ERXSTH, RX_START)?;
-            self.write_u16(ERXNDL, ERXNDH, RX_END)?;
-            // For tracking purposes, the ERXRDPT registers should additionally be programmed with
-            // the same value.
-            self.write_u16(ERXRDPTL, ERXRDPTH, RX_START)?;
-            self.next_packet = RX_START;
-
-            // No explicit action is required to initialize the transmission buffer.
-            self.write_u16(ETXSTL, ETXSTH, TX_START)?;
-        }
-
-        //
-        // MAC initialization
-        //
-        {
-            // Set the MARXEN bit in MACON1 to enable the MAC to receive frames.
-            self.write_control(MACON1, 1)?;
-
-            // Configure the PADCFG, TXCRCEN and FULDPX bits of MACON3.
-            //
-            // In this setup, we are:
-            // - enabling full duplex mode
-            // - enabling frame length checking
-            // - appending a CRC to transmitted frames
-            // - padding all short frames to 60 bytes and appending a CRC
-            const MACON3_MASK: u8 = 0b00110011;
-            self.write_control(MACON3, MACON3_MASK)?;
-
-            // Program the MAMXFL registers with the maximum frame length.
-            const MAX_FRAME_LENGTH: u16 = 1518;
-            self.write_u16(MAMXFLL, MAMXFLH, MAX_FRAME_LENGTH)?;
-
-            // Configure MABBIPG with recommended value for full-duplex mode.
-            self.write_control(MABBIPG, 0x15)?;
-
-            // Configure MAIPGL with recommended value.
-            self.write_control(MAIPGL, 0x06)?;
-
-            // Program the local MAC address
-            self.write_control(MAADR1, 0xff)?;
-            self.write_control(MAADR2, 0xca)?;
-            self.write_control(MAADR3, 0xde)?;
-            self.write_control(MAADR4, 0xee)?;
-            self.write_control(MAADR5, 0xff)?;
-            self.write_control(MAADR6, 0xc0)?;
-        }
-
-        self.write_control(ERXFCON, 0)?;
-
-        //
-        // PHY initialization
-        //
-        {
-            // For proper duplex operation, PHCON1.PDPXMD must also match MACON3.FULDPX.
-            self.write_phy(PHCON1, 0x0100)?;
+    /// Resets the device and configures it according to `config`.
+    ///
+    /// `delay` is only used to wait for the device to become ready after the soft reset, as
+    /// required by the errata sheet. Any [`DelayNs`] implementation works, so the driver is not
+    /// tied to a particular target.
+    ///
+    /// The driver settings in `config` replace those made with the setters. The driver is consumed
+    /// and returned in the [`Ready`] state. Since a reset leaves the device uninitialized, a
+    /// [`Ready`] driver may be initialized again, too; until then, receiving and transmitting fail
+    /// with [`Error::NotInitialized`].
+    ///
+    /// Fails with [`Error::DeviceNotResponding`] if EREVID reads back as 00h or FFh after the
+    /// reset, which usually means a wiring or power problem, and with
+    /// [`Error::UnsupportedRevision`] if EREVID holds a revision not listed in the errata. An
+    /// out-of-range [`Enc28j60Config::max_frame_length`] fails with [`Error::InvalidArgument`]
+    /// before the device is touched. The
+    /// [`InitError`] hands the driver back in the [`Uninit`] state, so the initialization can be
+    /// retried.
+    #[allow(clippy::type_complexity)]
+    pub fn initialize_with<D: DelayNs>(
+        mut self,
+        delay: &mut D,
+        config: &Enc28j60Config,
+    ) -> Result<
+        Enc28j60<SPI, INT, RST, Ready, DELAY>,
+        InitError<Enc28j60<SPI, INT, RST, Uninit, DELAY>, SPI::Error>,
+    > {
+        match self.configure(delay, config) {
+            Ok(()) => Ok(self.into_state()),
+            Err(error) => Err(InitError {
+                driver: self.into_state(),
+                error,
+            }),
+        }
+    }
 
-            // We are in full-duplex mode, but for sanitation reasons, we disable PHCON2.HDLDIS.
-            self.write_phy(PHCON2, 0x0100)?;
+    /// Moves the driver into the state `T`, keeping everything else.
+    fn into_state<T>(self) -> Enc28j60<SPI, INT, RST, T, DELAY> {
+        Enc28j60 {
+            spi: self.spi,
+            int: self.int,
+            reset: self.reset,
+            current_bank: self.current_bank,
+            next_packet: self.next_packet,
+            rx_start: self.rx_start,
+            rx_end: self.rx_end,
+            poll_limit: self.poll_limit,
+            reset_tx_logic: self.reset_tx_logic,
+            tx_retries: self.tx_retries,
+            max_frame_len: self.max_frame_len,
+            inter_packet_gap: self.inter_packet_gap,
+            macon4: self.macon4,
+            mac_address: self.mac_address,
+            duplex: self.duplex,
+            receive_filter: self.receive_filter,
+            hash_table: self.hash_table,
+            pattern_match: self.pattern_match,
+            tx_pending: self.tx_pending,
+            stats: self.stats,
+            initialized: self.initialized,
+            delay: self.delay,
+            state: PhantomData,
         }
+    }
 
-        // Issue interrupts when packets arrive. This allows users to wfi() in a loop to
-        // efficiently wait for incoming packets.
-        self.write_control(EIE, 0b1100_0000)?;
+    /// Returns the configuration the driver and device currently have.
+    fn config(&self) -> Enc28j60Config {
+        Enc28j60Config {
+            mac_address: self.mac_address,
+            duplex: self.duplex,
+            receive_filter: self.receive_filter,
+            max_frame_length: self.max_frame_len,
+            inter_packet_gap: self.inter_packet_gap,
+            macon4: self.macon4,
+            poll_limit: self.poll_limit,
+            transmit_reset: self.reset_tx_logic,
+            transmit_retries: self.tx_retries,
+        }
+    }
 
-        // At this point, the receive buffer has been initialized, MAC has been configured, and
-        // the default receive filter has been set up. We are ready to enable reception.
-        self.write_control(ECON1, 0b0000_0100)?;
+    /// Resets the device and configures it according to `config`. See
+    /// [`Enc28j60::initialize_with`].
+    fn configure<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        config: &Enc28j60Config,
+    ) -> Result<(), Error<SPI::Error>> {
+        let registers = config.registers()?;
+        self.max_frame_len = registers.max_frame_length;
+        self.inter_packet_gap = config.inter_packet_gap;
+        self.macon4 = config.macon4;
+        self.poll_limit = config.poll_limit;
+        self.reset_tx_logic = config.transmit_reset;
+        self.tx_retries = config.transmit_retries;
+        self.duplex = config.duplex;
 
+        self.reset_via_spi(delay)?;
+        // The reset clears the hash table and the pattern match filter.
+        self.hash_table = 0;
+        self.pattern_match = None;
+
+        // ESTAT.CLKRDY is not reliable after a soft reset on any known revision (see the errata),
+        // so the 1 ms wait above stands in for it.
+        sequence::check_revision(self.read_control(EREVID)?)?;
+
+        self.run(sequence::configure(config, &registers))?;
+        self.next_packet = RX_START;
+        self.rx_start = RX_START;
+        self.rx_end = RX_END;
+        self.mac_address = config.mac_address;
+        self.receive_filter = config.receive_filter;
+
+        self.initialized = true;
         Ok(())
     }
 
     /// Issues a system reset via the device's reset pin.
     ///
     /// Since the function can run at any time, it may be used to asynchronously reset the device.
+    /// The device must be initialized again afterwards.
     ///
-    pub fn reset<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), RST::Error> {
+    /// Returns once ESTAT.CLKRDY reports that the oscillator has started, polling at most
+    /// `poll_limit` times before failing with [`Error::Timeout`]. Fails with [`Error::Pin`] if the
+    /// reset pin cannot be driven.
+    ///
+    pub fn reset<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<SPI::Error>> {
+        self.initialized = false;
+        self.tx_pending = false;
+        // A reset clears ECON1, selecting bank 0.
+        self.current_bank = Some(Bank::Bank0);
+
         // Hold the RESET pin low for at least $t_{RSTLOW}$ ns
-        self.reset.set_low()?;
+        self.reset.set_low().map_err(|_| Error::Pin)?;
         delay.delay_ns(400);
-        self.reset.set_high()?;
+        self.reset.set_high().map_err(|_| Error::Pin)?;
 
         // After a System Reset, all PHY registers should not be read or written to until at least
         // 50 μs have passed since the Reset has ended.
         delay.delay_us(50);
 
-        Ok(())
+        // Unlike after a soft reset, ESTAT.CLKRDY is reliable after a hardware reset.
+        let clkrdy = Estat::CLKRDY.bits();
+        self.poll_until(ESTAT, clkrdy, clkrdy)
+    }
+
+    /// Like [`Enc28j60::reset`], but waits with the delay passed to
+    /// [`Enc28j60::new_with_delay`].
+    pub fn reset_with_stored_delay(&mut self) -> Result<(), Error<SPI::Error>>
+    where
+        DELAY: DelayNs,
+    {
+        self.with_stored_delay(|enc, delay| enc.reset(delay))
     }
 
     /// Issues a System Soft Reset via SPI by invoking SRC (System Reset Command).
@@ -164,45 +551,91 @@ where
     /// # Note
     ///
     /// There may be other SPI commands in progress, so the reset is not immediate. If you need
-    /// an immediate reset, use the `reset` function.
+    /// an immediate reset, use the `reset` function. The device must be initialized again
+    /// afterwards.
+    ///
+    /// Waits 1 ms using `delay` before returning, so the PHY registers may be accessed right
+    /// away.
     ///
-    pub fn reset_via_spi<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), SPI::Error> {
-        // Unlike other SPI commands, the SRC is only a single byte command and does not operate on
-        // any register.
-        self.spi.write(&[0xFF])?;
+    pub fn reset_via_spi<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<SPI::Error>> {
+        self.initialized = false;
+        self.tx_pending = false;
+        // A reset clears ECON1, selecting bank 0.
+        self.current_bank = Some(Bank::Bank0);
+
+        let result = self.spi.write(&[SRC_COMMAND]);
+        self.forget_bank_on_error(result)?;
 
         // Apply workaround from errata sheet: "After issuing the [SPI] Reset command, wait at
-        // least 1ms in firmware for the device to be ready."
+        // least 1ms in firmware for the device to be ready." This also covers the 50 μs after a
+        // System Reset during which the PHY registers must not be accessed.
         delay.delay_ms(1);
 
         Ok(())
     }
 
-    fn ensure_autoinc(&mut self) -> Result<(), SPI::Error> {
-        const AUTOINC_MASK: u8 = 0x80;
-        let cmd = [ECON2.opcode(Op::BFS), AUTOINC_MASK];
-        self.spi.write(&cmd)
+    /// Sets or clears ECON2.AUTOINC, which `initialize` sets.
+    ///
+    /// While the bit is set, ERDPT and EWRPT advance past every byte read from or written to the
+    /// buffer memory, so that each read or write continues where the last one left off. Receiving
+    /// and transmitting frames rely on this. Clear it only for random access to the buffer memory,
+    /// and set it again before receiving or transmitting.
+    pub fn set_autoinc(&mut self, enabled: bool) -> Result<(), Error<SPI::Error>> {
+        let autoinc = Econ2::AUTOINC.bits();
+        if enabled {
+            self.bit_field_set(ECON2, autoinc)
+        } else {
+            self.bit_field_clear(ECON2, autoinc)
+        }
     }
 
-    fn mem_read(&mut self, data: &mut [u8]) -> Result<(), SPI::Error> {
-        const RBM_MAGIC: u8 = 0x1a;
-        const OPCODE: u8 = (Op::RBM as u8) | RBM_MAGIC;
+    /// Reads `data.len()` bytes of buffer memory from ERDPT in a single RBM command. With
+    /// ECON2.AUTOINC set, consecutive calls read consecutive bytes.
+    fn mem_read(&mut self, data: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        let mut ops = [Operation::Write(&[RBM_COMMAND]), Operation::Read(data)];
+        let result = self.spi.transaction(&mut ops);
+        self.forget_bank_on_error(result)
+    }
 
-        let mut ops = [Operation::Write(&[OPCODE]), Operation::Read(data)];
-        self.spi.transaction(&mut ops)
+    /// Writes `data` to buffer memory at EWRPT in a single WBM command. With ECON2.AUTOINC set,
+    /// consecutive calls write consecutive bytes.
+    fn mem_write(&mut self, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        let mut ops = [Operation::Write(&[WBM_COMMAND]), Operation::Write(data)];
+        let result = self.spi.transaction(&mut ops);
+        self.forget_bank_on_error(result)
     }
 
-    fn mem_write(&mut self, data: &[u8]) -> Result<(), SPI::Error> {
-        const WBM_MAGIC: u8 = 0x1a;
-        const OPCODE: u8 = (Op::WBM as u8) | WBM_MAGIC;
+    /// Reads `buf.len()` bytes of buffer memory starting at `addr`, by pointing ERDPT at `addr`
+    /// and issuing a single RBM command.
+    ///
+    /// With ECON2.AUTOINC set, as after `initialize`, ERDPT advances with every byte. It wraps
+    /// from the end of the receive buffer (ERXND) back to its start (ERXST), and from 1FFFh back
+    /// to 0000h, so a read running past either end continues there. Without AUTOINC, every byte
+    /// is read from `addr`.
+    ///
+    /// `initialize` places the receive buffer at 0000h–0FFFh and the transmit buffer at
+    /// 1000h–1FFFh. ERDPT is left past the last byte read; receiving programs it again for each
+    /// packet.
+    pub fn read_memory(&mut self, addr: u16, buf: &mut [u8]) -> Result<(), Error<SPI::Error>> {
+        self.write_u16(ERDPTL, ERDPTH, addr & BUFFER_END)?;
+        self.mem_read(buf)
+    }
 
-        let mut ops = [Operation::Write(&[OPCODE]), Operation::Write(data)];
-        self.spi.transaction(&mut ops)
+    /// Writes `data` to buffer memory starting at `addr`, by pointing EWRPT at `addr` and issuing
+    /// a single WBM command.
+    ///
+    /// With ECON2.AUTOINC set, EWRPT advances with every byte and wraps from 1FFFh back to 0000h.
+    /// Writing to the receive buffer corrupts the packets waiting in it, and writing to the
+    /// transmit buffer while a frame is being transmitted corrupts that frame. See
+    /// [`Enc28j60::read_memory`] for the layout set up by `initialize`.
+    pub fn write_memory(&mut self, addr: u16, data: &[u8]) -> Result<(), Error<SPI::Error>> {
+        self.write_u16(EWRPTL, EWRPTH, addr & BUFFER_END)?;
+        self.mem_write(data)
     }
 
-    pub fn read_control(&mut self, reg: ControlRegister) -> Result<u8, SPI::Error> {
+    pub fn read_control(&mut self, reg: ControlRegister) -> Result<u8, Error<SPI::Error>> {
         if let Some(bank) = reg.bank()
-            && self.current_bank != bank
+            && self.current_bank != Some(bank)
         {
             self.set_bank(bank)?;
         }
@@ -210,7 +643,8 @@ where
         let mut buf = [0u8; 3];
         let command = [reg.opcode(Op::RCR), 0u8];
 
-        self.spi.transfer(&mut buf, &command)?;
+        let result = self.spi.transfer(&mut buf, &command);
+        self.forget_bank_on_error(result)?;
 
         if reg.shifts_dummy_byte() {
             Ok(buf[2])
@@ -219,18 +653,145 @@ where
         }
     }
 
-    fn write_control(&mut self, reg: ControlRegister, data: u8) -> Result<(), SPI::Error> {
+    /// Reads `reg` and interprets the value as `T`, typically one of the flag types in
+    /// [`register`](crate::register), such as [`Econ1`].
+    pub fn read_reg<T: From<u8>>(&mut self, reg: ControlRegister) -> Result<T, Error<SPI::Error>> {
+        Ok(T::from(self.read_control(reg)?))
+    }
+
+    fn write_control(&mut self, reg: ControlRegister, data: u8) -> Result<(), Error<SPI::Error>> {
         if let Some(bank) = reg.bank()
-            && self.current_bank != bank
+            && self.current_bank != Some(bank)
         {
             self.set_bank(bank)?;
         }
 
         let buf = [reg.opcode(Op::WCR), data];
-        self.spi.write(&buf)
+        let result = self.spi.write(&buf);
+        self.forget_bank_on_error(result)
+    }
+
+    /// Programs `mac` as the station MAC address.
+    ///
+    /// The MAC address should not be changed while reception is enabled, since the receive
+    /// filter could observe a partially written address. If ECON1.RXEN is set, reception is
+    /// disabled for the duration of the write and re-enabled afterwards.
+    pub fn set_mac_address(&mut self, mac: &[u8; 6]) -> Result<(), Error<SPI::Error>> {
+        let rx_enabled = self.read_reg::<Econ1>(ECON1)?.contains(Econ1::RXEN);
+        if rx_enabled {
+            self.bit_field_clear(ECON1, Econ1::RXEN.bits())?;
+        }
+
+        for (reg, octet) in MAADR.into_iter().zip(mac) {
+            self.write_control(reg, *octet)?;
+        }
+        self.mac_address = *mac;
+
+        if rx_enabled {
+            self.bit_field_set(ECON1, Econ1::RXEN.bits())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the station MAC address currently programmed into the MAADR registers.
+    pub fn read_mac_address(&mut self) -> Result<[u8; 6], Error<SPI::Error>> {
+        let mut mac = [0u8; 6];
+        for (reg, octet) in MAADR.into_iter().zip(&mut mac) {
+            *octet = self.read_control(reg)?;
+        }
+        Ok(mac)
+    }
+
+    /// Reports whether the device is asserting its INT pin, which is active low.
+    ///
+    /// `initialize` enables the packet pending interrupt, so the pin is asserted while packets
+    /// are waiting in the receive buffer. A typical receive loop waits for the pin, for example by
+    /// sleeping with `wfi()` until an edge interrupt fires, then drains the pending packets:
+    ///
+    /// ```ignore
+    /// loop {
+    ///     while !enc.interrupt_pending()? {
+    ///         cortex_m::asm::wfi();
+    ///     }
+    ///
+    ///     let flags = enc.read_interrupts()?;
+    ///     if flags.contains(InterruptFlags::PACKET) {
+    ///         enc.receive_all(|packet, _len| handle(packet))?;
+    ///     }
+    ///     enc.clear_interrupts(flags)?;
+    /// }
+    /// ```
+    pub fn interrupt_pending(&mut self) -> Result<bool, INT::Error> {
+        self.int.is_low()
+    }
+
+    /// Reads the status flags from ESTAT.
+    pub fn read_estat(&mut self) -> Result<Estat, Error<SPI::Error>> {
+        self.read_reg(ESTAT)
+    }
+
+    /// Programs MACON4, which controls deferral and backoff in half-duplex mode.
+    ///
+    /// Setting [`Macon4::DEFER`] can help with legacy hubs which keep the medium busy for long
+    /// periods, since the transmission is then never aborted for excessive deferral. The other
+    /// bits break conformance with IEEE 802.3 and should normally be left clear.
+    ///
+    /// The value is kept and programmed again by `initialize` in half-duplex mode, so it may be set
+    /// before the device is initialized.
+    pub fn set_macon4(&mut self, macon4: Macon4) -> Result<(), Error<SPI::Error>> {
+        self.macon4 = macon4;
+        self.write_control(MACON4, macon4.bits())
+    }
+
+    /// Programs EIE with the interrupts to raise on the INT pin. [`Eie::INTIE`] must be set for
+    /// any of them to reach the pin.
+    pub fn set_interrupt_enable(&mut self, eie: Eie) -> Result<(), Error<SPI::Error>> {
+        self.write_control(EIE, eie.bits())
+    }
+
+    /// Reads the interrupt flags from EIR.
+    pub fn read_interrupts(&mut self) -> Result<InterruptFlags, Error<SPI::Error>> {
+        self.read_reg(EIR)
+    }
+
+    /// Clears the given interrupt flags in EIR.
+    ///
+    /// [`InterruptFlags::PACKET`] and [`InterruptFlags::LINK`] cannot be cleared this way and are
+    /// ignored; see [`InterruptFlags`].
+    pub fn clear_interrupts(&mut self, flags: InterruptFlags) -> Result<(), Error<SPI::Error>> {
+        self.bit_field_clear(EIR, flags.clear_mask())
+    }
+
+    /// Reports whether the receive buffer overflowed since the last call, clearing EIR.RXERIF.
+    ///
+    /// Incoming packets are dropped while the receive buffer is full, or while EPKTCNT is at 255.
+    /// No recovery is needed besides clearing the flag: reception resumes by itself as soon as
+    /// packets are read and space is freed. [`Enc28j60::receive`] checks and clears the flag as
+    /// well, and reports it in [`ReceivedPacket::overflowed`]. Either way, the overflow is counted
+    /// in [`Stats::rx_overflows`].
+    pub fn rx_overflow(&mut self) -> Result<bool, Error<SPI::Error>> {
+        let overflowed = self.read_interrupts()?.contains(InterruptFlags::RX_ERROR);
+        if overflowed {
+            self.clear_interrupts(InterruptFlags::RX_ERROR)?;
+            self.stats.count_overflow();
+        }
+        Ok(overflowed)
     }
 
-    pub fn read_phy(&mut self, reg: PhyRegister) -> Result<u16, SPI::Error> {
+    /// Returns the number of times incoming packets were dropped because the receive buffer was
+    /// full, including an overflow which has not been noticed yet.
+    ///
+    /// The device only flags that an overflow happened (EIR.RXERIF), not how many packets it
+    /// dropped, so this is a lower bound on the number of dropped packets. Packets rejected by
+    /// the receive filters, including those discarded for an invalid CRC, are not dropped in this
+    /// sense and are not counted. The count is reset by [`Enc28j60::reset_stats`].
+    pub fn packets_dropped(&mut self) -> Result<u32, Error<SPI::Error>> {
+        self.rx_overflow()?;
+        Ok(self.stats.rx_overflows)
+    }
+
+    pub fn read_phy(&mut self, reg: PhyRegister) -> Result<u16, Error<SPI::Error>> {
         // 1. Write address to MIREGADR
         self.write_control(MIREGADR, reg.addr())?;
 
@@ -238,12 +799,7 @@ where
         self.write_control(MICMD, 0b01)?;
 
         // 3. Poll MISTAT.BUSY to be certain that the operation is complete
-        loop {
-            let mistat = self.read_control(MISTAT)?;
-            if (mistat & 0b01) == 0 {
-                break;
-            }
-        }
+        self.poll_until(MISTAT, 0b01, 0)?;
 
         // 4. Clear MICMD.MIIRD
         self.write_control(MICMD, 0b00)?;
@@ -252,7 +808,7 @@ where
         self.read_u16(MIRDL, MIRDH)
     }
 
-    fn write_phy(&mut self, reg: PhyRegister, data: u16) -> Result<(), SPI::Error> {
+    fn write_phy(&mut self, reg: PhyRegister, data: u16) -> Result<(), Error<SPI::Error>> {
         // 1. Write address to MIREGADR
         self.write_control(MIREGADR, reg.addr())?;
 
@@ -261,145 +817,156 @@ where
         self.write_u16(MIWRL, MIWRH, data)
     }
 
-    //
-    // Network function
-    //
+    /// Reads the silicon revision from EREVID.
+    pub fn revision(&mut self) -> Result<Revision, Error<SPI::Error>> {
+        Ok(Revision::from_erevid(self.read_control(EREVID)?))
+    }
 
-    /// Receive a single packet into `buf`. Returns number of bytes written into `buf`.
-    pub fn receive(&mut self, buf: &mut [u8]) -> Result<usize, SPI::Error> {
-        let packet_count = self.read_control(EPKTCNT)?;
-        if packet_count == 0 {
-            return Ok(0);
+    /// Writes `pattern` to the buffer memory at `start` and reads it back, reporting whether it
+    /// was read back intact.
+    ///
+    /// The original contents of the tested bytes are saved beforehand and restored afterwards, so
+    /// any region may be tested, even on an initialized device. Reception is disabled for the
+    /// duration of the test, so that the restored contents cannot overwrite incoming packets.
+    /// A pattern extending past the end of the buffer memory wraps around to its start.
+    pub fn memory_test(&mut self, start: u16, pattern: &[u8]) -> Result<bool, Error<SPI::Error>> {
+        let rx_enabled = self.read_reg::<Econ1>(ECON1)?.contains(Econ1::RXEN);
+        if rx_enabled {
+            self.bit_field_clear(ECON1, Econ1::RXEN.bits())?;
         }
 
-        // Start reading from the beginning of the next Packet Pointer
-        self.write_u16(ERDPTL, ERDPTH, self.next_packet)?;
-
-        // Read the receive status vector (6 bytes)
-        // Format: [next_packet_ptr(2), byte_count(2), status(2)]
-        let mut rsv = [0u8; 6];
-        self.mem_read(&mut rsv)?;
-
-        // Extract next packet pointer and byte count (little-endian)
-        let next_packet = u16::from_le_bytes([rsv[0], rsv[1]]);
-        let byte_count = u16::from_le_bytes([rsv[2], rsv[3]]) as usize;
-
-        // The byte count includes the 4-byte CRC, so subtract it for payload length
-        let payload_len = byte_count.saturating_sub(4);
-        let copy_len = min(payload_len, buf.len());
-
-        // Read the packet payload into the buffer
-        if copy_len > 0 {
-            self.mem_read(&mut buf[..copy_len])?;
+        // The read pointer wraps from ERXND to ERXST, so a chunk must not extend past ERXND.
+        let rx_end = self.read_u16(ERXNDL, ERXNDH)?;
+
+        const CHUNK_LEN: usize = 32;
+        let mut saved = [0u8; CHUNK_LEN];
+        let mut readback = [0u8; CHUNK_LEN];
+        let mut intact = true;
+        let mut offset = 0;
+        while offset < pattern.len() {
+            let addr = start.wrapping_add(offset as u16) & BUFFER_END;
+            let boundary = if addr <= rx_end { rx_end } else { BUFFER_END };
+            let len = min(pattern.len() - offset, CHUNK_LEN).min(usize::from(boundary - addr) + 1);
+            let chunk = &pattern[offset..offset + len];
+
+            self.write_u16(ERDPTL, ERDPTH, addr)?;
+            self.mem_read(&mut saved[..len])?;
+
+            self.write_u16(EWRPTL, EWRPTH, addr)?;
+            self.mem_write(chunk)?;
+            self.write_u16(ERDPTL, ERDPTH, addr)?;
+            self.mem_read(&mut readback[..len])?;
+            intact &= readback[..len] == *chunk;
+
+            self.write_u16(EWRPTL, EWRPTH, addr)?;
+            self.mem_write(&saved[..len])?;
+
+            offset += len;
         }
 
-        // Packet is larger than buffer
-        // Skip the remaining bytes in order to properly advance the read pointer
-        if payload_len > copy_len {
-            let mut remaining = payload_len - copy_len;
-            let mut dummy = [0u8; 64];
-            while remaining > 0 {
-                let chunk_size = min(remaining, dummy.len());
-                self.mem_read(&mut dummy[..chunk_size])?;
-                remaining -= chunk_size;
-            }
+        if rx_enabled {
+            self.bit_field_set(ECON1, Econ1::RXEN.bits())?;
         }
 
-        // From data sheet: "The host controller will save the next Packet Pointer ..."
-        self.next_packet = next_packet;
-
-        // Update ERXRDPT to free the memory used by this packet
-        // ERXRDPT should point to the byte before the next packet's start
-        let erx_start = self.read_u16(ERXSTL, ERXSTH)?;
-        let erx_end = self.read_u16(ERXNDL, ERXNDH)?;
-
-        let new_rdpt = if next_packet == erx_start {
-            // Wrap-around case: next packet is at the start, so point to the end
-            erx_end
-        } else {
-            // Normal case: point to the byte before the next packet
-            next_packet - 1
-        };
+        Ok(intact)
+    }
 
-        self.write_u16(ERXRDPTL, ERXRDPTH, new_rdpt)?;
+    /// Runs a series of sanity checks on the device and returns the results.
+    ///
+    /// This reads the silicon revision, ESTAT.CLKRDY and the PHY identifier, and runs
+    /// [`Enc28j60::memory_test`] on the last bytes of the buffer memory. Including the results in
+    /// bug reports helps to rule out wiring and SPI timing problems.
+    pub fn self_test(&mut self) -> Result<Diagnostics, Error<SPI::Error>> {
+        let revision = self.revision()?;
+
+        let clock_ready = self.read_estat()?.clk_ready();
+
+        let phy_id = (self.read_phy(PHID1)?, self.read_phy(PHID2)?);
+
+        const PATTERN: [u8; 8] = [0x00, 0xff, 0x55, 0xaa, 0x0f, 0xf0, 0x5a, 0xa5];
+        const START: u16 = BUFFER_END + 1 - PATTERN.len() as u16;
+        let memory_ok = self.memory_test(START, &PATTERN)?;
+
+        Ok(Diagnostics {
+            revision,
+            clock_ready,
+            phy_id,
+            memory_ok,
+        })
+    }
 
-        // Decrement the packet count by setting ECON2.PKTDEC
-        const PKTDEC_MASK: u8 = 0b0100_0000;
-        let cmd = [ECON2.opcode(Op::BFS), PKTDEC_MASK];
-        self.spi.write(&cmd)?;
+    //
+    // DMA
+    //
 
-        Ok(copy_len)
+    /// Computes the checksum of the buffer memory from `start` to `end` inclusive with the DMA
+    /// checksum engine.
+    ///
+    /// The result is the 16-bit one's complement of the one's complement sum of the bytes taken as
+    /// big-endian words, with a zero byte appended to an odd-length range, as used by IP, UDP and
+    /// TCP. Its high byte comes first when it is written into a header. A range which crosses ERXND
+    /// wraps around to ERXST, like a packet in the receive buffer.
+    pub fn checksum(&mut self, start: u16, end: u16) -> Result<u16, Error<SPI::Error>> {
+        // Setting CSUMEN computes the checksum instead of copying memory.
+        self.run_dma(start, end, Econ1::CSUMEN)?;
+        self.bit_field_clear(ECON1, Econ1::CSUMEN.bits())?;
+
+        self.read_u16(EDMACSL, EDMACSH)
     }
 
-    /// Transmit a packet with the given source MAC, destination MAC, EtherType, and data payload.
-    /// The data should include the EtherType/Length field and payload.
-    pub fn transmit(
+    /// Copies the buffer memory from `src_start` to `src_end` inclusive to `dst` with the DMA
+    /// controller, without passing it through the SPI bus.
+    ///
+    /// A source range which crosses ERXND wraps around to ERXST, so a received packet can be
+    /// copied to the transmit buffer for forwarding as it is. The buffer memory cannot be read or
+    /// written over SPI while the copy runs; this returns once it has completed.
+    pub fn dma_copy(
         &mut self,
-        dst: &[u8; 6],
-        src: &[u8; 6],
-        ether_type: u16,
-        data: &[u8],
-    ) -> Result<(), SPI::Error> {
-        // 1a. Read current ETXST to know where to write
-        let tx_start = self.read_u16(ETXSTL, ETXSTH)?;
-
-        // 1b. Set up write pointer to tx_start
-        self.write_u16(EWRPTL, EWRPTH, tx_start)?;
-
-        // 2a. Write the per-packet control byte
-        let control = [0u8];
-        self.mem_write(&control)?;
-
-        // 2b. Write the Ethernet frame header
-        self.mem_write(dst)?;
-        self.mem_write(src)?;
-        self.mem_write(&ether_type.to_be_bytes())?;
-
-        // 2c. Write the data
-        self.mem_write(data)?;
-
-        // 3. Appropriately program the ETXND Pointer.
-        // It should point to the last byte in the data payload.
-        let packet_len = control.len() + src.len() + dst.len() + data.len();
-        let tx_end = tx_start + (packet_len as u16) - 1;
-        self.write_u16(ETXNDL, ETXNDH, tx_end)?;
-
-        // 4. Clear EIR.TXIF. For now, we do not enable interrupts (EIE.TXIE and EIE.INTIE).
-        const TXIF_MASK: u8 = 0b0000_1000;
-        let cmd = [EIR.opcode(Op::BFC), TXIF_MASK];
-        self.spi.write(&cmd)?;
-
-        // 5. Start the transmission process by setting ECON1.TXRTS.
-        const TXRTS_MASK: u8 = 0b0000_1000;
-        let cmd = [ECON1.opcode(Op::BFS), TXRTS_MASK];
-        self.spi.write(&cmd)?;
-
-        // Wait for transmission to complete
-        loop {
-            let econ1 = self.read_control(ECON1)?;
-            if (econ1 & TXRTS_MASK) == 0 {
-                break;
-            }
-        }
+        src_start: u16,
+        src_end: u16,
+        dst: u16,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.write_u16(EDMADSTL, EDMADSTH, dst)?;
+        self.run_dma(src_start, src_end, Econ1::empty())
+    }
 
-        // Check if transmission was successful
-        const TXABRT_MASK: u8 = 0b0000_0010;
-        let estat = self.read_control(ESTAT)?;
-        if (estat & TXABRT_MASK) != 0 {
-            // Aborted. Clear flag and log error for now.
-            let cmd = [ESTAT.opcode(Op::BFC), TXABRT_MASK];
-            self.spi.write(&cmd)?;
-            // defmt::error!("transmit: aborted");
-        }
+    /// Runs the DMA over `start..=end` with `flags` set in ECON1 alongside DMAST, and waits for it
+    /// to complete.
+    fn run_dma(&mut self, start: u16, end: u16, flags: Econ1) -> Result<(), Error<SPI::Error>> {
+        self.write_u16(EDMASTL, EDMASTH, start)?;
+        self.write_u16(EDMANDL, EDMANDH, end)?;
 
-        Ok(())
+        // The device clears DMAST and sets EIR.DMAIF once the operation completes.
+        self.bit_field_set(ECON1, (flags | Econ1::DMAST).bits())?;
+        self.poll_until(ECON1, Econ1::DMAST.bits(), 0)?;
+        self.clear_interrupts(InterruptFlags::DMA)
     }
 
     //
     // Helper function
     //
 
-    fn read_u16(&mut self, lo: ControlRegister, hi: ControlRegister) -> Result<u16, SPI::Error> {
+    /// Reads `reg` until the bits in `mask` equal `expected`, or fails with [`Error::Timeout`]
+    /// after `poll_limit` reads.
+    fn poll_until(
+        &mut self,
+        reg: ControlRegister,
+        mask: u8,
+        expected: u8,
+    ) -> Result<(), Error<SPI::Error>> {
+        for _ in 0..self.poll_limit {
+            if (self.read_control(reg)? & mask) == expected {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    fn read_u16(
+        &mut self,
+        lo: ControlRegister,
+        hi: ControlRegister,
+    ) -> Result<u16, Error<SPI::Error>> {
         let lo = self.read_control(lo)? as u16;
         let hi = self.read_control(hi)? as u16;
         Ok(lo | (hi << 8))
@@ -410,21 +977,2576 @@ where
         lo: ControlRegister,
         hi: ControlRegister,
         val: u16,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), Error<SPI::Error>> {
         self.write_control(lo, (val & 0xff) as u8)?;
         self.write_control(hi, (val >> 8) as u8)?;
         Ok(())
     }
 
-    fn set_bank(&mut self, bank: Bank) -> Result<(), SPI::Error> {
-        let mask = 0b11;
-        let command = [ECON1.opcode(Op::BFC), mask];
-        self.spi.write(&command)?;
+    /// Sets the bits in `mask` of an ETH register. Not available for MAC and MII registers.
+    fn bit_field_set(&mut self, reg: ControlRegister, mask: u8) -> Result<(), Error<SPI::Error>> {
+        if let Some(bank) = reg.bank()
+            && self.current_bank != Some(bank)
+        {
+            self.set_bank(bank)?;
+        }
+
+        let cmd = [reg.opcode(Op::BFS), mask];
+        let result = self.spi.write(&cmd);
+        self.forget_bank_on_error(result)
+    }
+
+    /// Clears the bits in `mask` of an ETH register. Not available for MAC and MII registers.
+    fn bit_field_clear(&mut self, reg: ControlRegister, mask: u8) -> Result<(), Error<SPI::Error>> {
+        if let Some(bank) = reg.bank()
+            && self.current_bank != Some(bank)
+        {
+            self.set_bank(bank)?;
+        }
+
+        let cmd = [reg.opcode(Op::BFC), mask];
+        let result = self.spi.write(&cmd);
+        self.forget_bank_on_error(result)
+    }
+
+    /// Selects `bank`, assuming `current_bank` is the bank selected now.
+    ///
+    /// Only the bank select bits which differ are cleared or set, so switching banks takes a
+    /// single command unless both bits change, or the current bank is unknown. This brings
+    /// `initialize` in full-duplex mode down from 44 to 39 SPI transactions.
+    fn set_bank(&mut self, bank: Bank) -> Result<(), Error<SPI::Error>> {
+        // Until both writes succeed, the selected bank is unknown.
+        for command in bank_switch_commands(self.current_bank.take(), bank)
+            .into_iter()
+            .flatten()
+        {
+            self.spi.write(&command)?;
+        }
+        self.current_bank = Some(bank);
+
+        Ok(())
+    }
 
-        let command = [ECON1.opcode(Op::BFS), (bank as u8) & mask];
-        self.spi.write(&command)?;
-        self.current_bank = bank;
+    /// Forgets the selected bank if `result` is an error, since a transfer which failed midway
+    /// may have left the device in any bank.
+    fn forget_bank_on_error<T>(
+        &mut self,
+        result: Result<T, SPI::Error>,
+    ) -> Result<T, Error<SPI::Error>> {
+        if result.is_err() {
+            self.current_bank = None;
+        }
+        Ok(result?)
+    }
 
+    /// Runs the register accesses of a sequence shared with the asynchronous driver.
+    fn run(&mut self, steps: impl IntoIterator<Item = Step>) -> Result<(), Error<SPI::Error>> {
+        for step in steps {
+            match step {
+                Step::Write(reg, data) => self.write_control(reg, data)?,
+                Step::Set(reg, mask) => self.bit_field_set(reg, mask)?,
+                Step::Clear(reg, mask) => self.bit_field_clear(reg, mask)?,
+                Step::WritePhy(reg, data) => self.write_phy(reg, data)?,
+            }
+        }
         Ok(())
     }
 }
+
+impl<SPI, INT, RST, DELAY> Enc28j60<SPI, INT, RST, Ready, DELAY>
+where
+    SPI: SpiDevice,
+    INT: InputPin,
+    RST: OutputPin,
+{
+    /// Recovers a device which stopped responding, for example after a brown-out, by resetting it
+    /// through the reset pin and initializing it again with the current configuration.
+    ///
+    /// The configuration is the one last passed to `initialize`, updated by the setters, the MAC
+    /// address set with [`Enc28j60::set_mac_address`] and the receive filters set since,
+    /// including the hash table and the pattern match filter. Packets waiting in the receive
+    /// buffer are lost.
+    pub fn recover<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<SPI::Error>> {
+        let config = self.config();
+        let hash_table = self.hash_table;
+        let pattern_match = self.pattern_match;
+
+        // The pattern match filter is only enabled once its registers have been programmed again.
+        // Meanwhile, the hash table filter stays enabled with the table the reset cleared, which
+        // accepts nothing by itself, so that the filter never becomes empty and accepts every
+        // frame.
+        let mut initial = config;
+        initial.receive_filter.remove(ReceiveFilter::PATTERN_MATCH);
+        if config.receive_filter != ReceiveFilter::empty() {
+            initial.receive_filter.insert(ReceiveFilter::HASH_TABLE);
+        }
+        self.reset(delay)?;
+        self.configure(delay, &initial)?;
+
+        self.write_hash_table(hash_table)?;
+        if let Some(PatternMatch {
+            offset,
+            mask,
+            checksum,
+        }) = pattern_match
+        {
+            self.set_pattern_match(offset, mask, checksum)?;
+        }
+        self.set_receive_filter(config.receive_filter)
+    }
+
+    /// Like [`Enc28j60::recover`], but waits with the delay passed to
+    /// [`Enc28j60::new_with_delay`].
+    pub fn recover_with_stored_delay(&mut self) -> Result<(), Error<SPI::Error>>
+    where
+        DELAY: DelayNs,
+    {
+        self.with_stored_delay(|enc, delay| enc.recover(delay))
+    }
+
+    /// Returns the receive filters last programmed into ERXFCON.
+    pub fn receive_filter(&self) -> ReceiveFilter {
+        self.receive_filter
+    }
+
+    /// Programs the receive filters in `ERXFCON`.
+    pub fn set_receive_filter(&mut self, filter: ReceiveFilter) -> Result<(), Error<SPI::Error>> {
+        self.write_control(ERXFCON, filter.bits())?;
+        self.receive_filter = filter;
+        Ok(())
+    }
+
+    /// Sets or clears [`ReceiveFilter::CRC_CHECK`] in the current receive filter, leaving the other
+    /// filters as they are.
+    ///
+    /// While set, the device discards frames with an invalid CRC before they take up space in the
+    /// receive buffer. This is independent of [`RxStatus::crc_error`] in the receive status vector,
+    /// which only reports CRC errors of frames that were kept.
+    pub fn set_crc_check(&mut self, enabled: bool) -> Result<(), Error<SPI::Error>> {
+        let mut filter = self.receive_filter;
+        if enabled {
+            filter.insert(ReceiveFilter::CRC_CHECK);
+        } else {
+            filter.remove(ReceiveFilter::CRC_CHECK);
+        }
+        self.set_receive_filter(filter)
+    }
+
+    /// Enables or disables promiscuous mode.
+    ///
+    /// When enabled, every frame is accepted. When disabled, only frames addressed to the local
+    /// MAC address or to the broadcast address with a valid CRC are accepted. This overrides any
+    /// filter previously set with [`Enc28j60::set_receive_filter`].
+    pub fn set_promiscuous(&mut self, enabled: bool) -> Result<(), Error<SPI::Error>> {
+        let filter = if enabled {
+            ReceiveFilter::empty()
+        } else {
+            ReceiveFilter::UNICAST | ReceiveFilter::BROADCAST | ReceiveFilter::CRC_CHECK
+        };
+        self.set_receive_filter(filter)
+    }
+
+    /// Accepts frames sent to the multicast group `mac` using the hash table filter.
+    ///
+    /// The hash table is imperfect: frames to other addresses which hash to the same index are
+    /// also accepted, so higher layers must still check the destination address.
+    pub fn add_multicast_group(&mut self, mac: &[u8; 6]) -> Result<(), Error<SPI::Error>> {
+        let index = multicast_hash_index(mac);
+        let reg = EHT[usize::from(index >> 3)];
+        self.bit_field_set(reg, 1 << (index & 0b111))?;
+        self.hash_table |= 1 << index;
+
+        self.bit_field_set(ERXFCON, ReceiveFilter::HASH_TABLE.bits())?;
+        self.receive_filter.insert(ReceiveFilter::HASH_TABLE);
+        Ok(())
+    }
+
+    /// Removes every multicast group added with [`Enc28j60::add_multicast_group`] and disables the
+    /// hash table filter.
+    pub fn clear_multicast_groups(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.bit_field_clear(ERXFCON, ReceiveFilter::HASH_TABLE.bits())?;
+        self.receive_filter.remove(ReceiveFilter::HASH_TABLE);
+        self.write_hash_table(0)
+    }
+
+    /// Accepts exactly the frames sent to one of `addrs`, as far as the filters allow, replacing
+    /// the current receive filters and hash table.
+    ///
+    /// The local MAC address enables the unicast filter and the broadcast address the broadcast
+    /// filter. Any other address, unicast or multicast, sets its bit in the hash table and enables
+    /// the hash table filter. The filters are combined with OR semantics (see [`ReceiveFilter`]),
+    /// and [`ReceiveFilter::CRC_CHECK`] is kept as it was. An empty `addrs` enables the hash table
+    /// filter with an empty table, so that no frame is accepted.
+    ///
+    /// Like with [`Enc28j60::add_multicast_group`], addresses which share a hash bit with one of
+    /// `addrs` are accepted as well.
+    pub fn accept(&mut self, addrs: &[[u8; 6]]) -> Result<(), Error<SPI::Error>> {
+        let mut filter = ReceiveFilter::empty();
+        let mut table = 0u64;
+        for addr in addrs {
+            if *addr == self.mac_address {
+                filter.insert(ReceiveFilter::UNICAST);
+            } else if *addr == [0xff; 6] {
+                filter.insert(ReceiveFilter::BROADCAST);
+            } else {
+                table |= 1 << multicast_hash_index(addr);
+            }
+        }
+        if table != 0 || filter == ReceiveFilter::empty() {
+            filter.insert(ReceiveFilter::HASH_TABLE);
+        }
+        if self.receive_filter.contains(ReceiveFilter::CRC_CHECK) {
+            filter.insert(ReceiveFilter::CRC_CHECK);
+        }
+
+        // Disable the hash table filter while the table is being reprogrammed.
+        self.bit_field_clear(ERXFCON, ReceiveFilter::HASH_TABLE.bits())?;
+        self.receive_filter.remove(ReceiveFilter::HASH_TABLE);
+        self.write_hash_table(table)?;
+        self.set_receive_filter(filter)
+    }
+
+    /// Programs EHT0-EHT7 with `table`, EHT0 holding the least significant byte.
+    fn write_hash_table(&mut self, table: u64) -> Result<(), Error<SPI::Error>> {
+        for (reg, byte) in EHT.into_iter().zip(table.to_le_bytes()) {
+            self.write_control(reg, byte)?;
+        }
+        self.hash_table = table;
+        Ok(())
+    }
+
+    /// Accepts frames matching a byte pattern using the pattern match filter.
+    ///
+    /// `offset` is the start of the 64-byte window, counted from the first byte of the destination
+    /// address. Bit `n` of `mask` selects byte `offset + n` of the frame. A frame is accepted when
+    /// the checksum of the selected bytes equals `checksum`, which
+    /// [`pattern_match_checksum`](crate::pattern_match_checksum) computes from the pattern.
+    /// Frames too short to contain the whole window are rejected.
+    ///
+    /// The checksum is not a cryptographic match, so different patterns may be accepted too.
+    pub fn set_pattern_match(
+        &mut self,
+        offset: u16,
+        mask: u64,
+        checksum: u16,
+    ) -> Result<(), Error<SPI::Error>> {
+        // Disable the filter while it is being reprogrammed.
+        self.bit_field_clear(ERXFCON, ReceiveFilter::PATTERN_MATCH.bits())?;
+        self.receive_filter.remove(ReceiveFilter::PATTERN_MATCH);
+
+        self.write_u16(EPMOL, EPMOH, offset)?;
+        for (reg, byte) in EPMM.into_iter().zip(mask.to_le_bytes()) {
+            self.write_control(reg, byte)?;
+        }
+        self.write_u16(EPMCSL, EPMCSH, checksum)?;
+        self.pattern_match = Some(PatternMatch {
+            offset,
+            mask,
+            checksum,
+        });
+
+        self.bit_field_set(ERXFCON, ReceiveFilter::PATTERN_MATCH.bits())?;
+        self.receive_filter.insert(ReceiveFilter::PATTERN_MATCH);
+        Ok(())
+    }
+
+    /// Accepts only Magic Packets for the station MAC address, and raises an interrupt on the INT
+    /// pin when one arrives.
+    ///
+    /// The Magic Packet filter matches frames addressed to the station, whose payload holds six
+    /// `0xff` bytes followed by sixteen repetitions of the station MAC address, whatever their
+    /// EtherType. It checks the address programmed with [`Enc28j60::set_mac_address`], so program
+    /// that first if it changed. Frames with an invalid CRC are discarded.
+    ///
+    /// The device cannot receive in power-save mode, so it must stay powered up while the host
+    /// sleeps. A typical wake sequence is:
+    ///
+    /// 1. Call this method, then put the host to sleep, waiting for the INT pin to go low.
+    /// 2. On wake-up, receive and discard the Magic Packet, which clears the interrupt.
+    /// 3. Restore the previous filter with [`Enc28j60::set_receive_filter`].
+    pub fn enable_wake_on_lan(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.set_receive_filter(ReceiveFilter::MAGIC_PACKET | ReceiveFilter::CRC_CHECK)?;
+        self.bit_field_set(EIE, (Eie::INTIE | Eie::PKTIE).bits())
+    }
+
+    /// Reports whether the Ethernet link is currently up, as indicated by PHSTAT2.LSTAT.
+    ///
+    /// PHSTAT2.LSTAT reflects the link state at the time of the read, so a brief loss of link
+    /// between two calls goes unnoticed. PHSTAT1.LLSTAT instead latches low when the link goes
+    /// down and stays low until PHSTAT1 is read; see [`Enc28j60::link_down_latched`].
+    pub fn link_up(&mut self) -> Result<bool, Error<SPI::Error>> {
+        Ok(self.link_status()?.up)
+    }
+
+    /// Reads the link state and duplex mode from PHSTAT2.
+    pub fn link_status(&mut self) -> Result<LinkStatus, Error<SPI::Error>> {
+        Ok(LinkStatus::from_phstat2(self.read_phy(PHSTAT2)?))
+    }
+
+    /// Reports whether the link went down since the previous call, as latched by PHSTAT1.LLSTAT.
+    ///
+    /// LLSTAT clears when the link goes down and stays clear until PHSTAT1 is read, after which
+    /// it follows the link again. Each call therefore reports a loss of link at most once, even
+    /// one too brief for [`Enc28j60::link_up`] to catch between two polls; call `link_up`
+    /// afterwards to learn whether the link has come back. Since LLSTAT is clear after a reset,
+    /// the first call after [`Enc28j60::initialize`] reports `true`.
+    pub fn link_down_latched(&mut self) -> Result<bool, Error<SPI::Error>> {
+        let phstat1 = PhStat1::from(self.read_phy(PHSTAT1)?);
+        Ok(!phstat1.contains(PhStat1::LLSTAT))
+    }
+
+    /// Raises an interrupt on the INT pin when the link goes up or down.
+    ///
+    /// This sets EIE.LINKIE and EIE.INTIE without disturbing the other enabled interrupts. Use
+    /// [`Enc28j60::take_link_change`] to acknowledge the interrupt.
+    pub fn enable_link_interrupt(&mut self) -> Result<(), Error<SPI::Error>> {
+        // Set PHIE.PLNKIE and PHIE.PGEIE to forward PHY link changes to the MAC.
+        self.write_phy(PHIE, (Phie::PLNKIE | Phie::PGEIE).bits())?;
+
+        self.bit_field_set(EIE, (Eie::INTIE | Eie::LINKIE).bits())
+    }
+
+    /// Reports whether the link changed since the last call, clearing the latched PHIR.PLNKIF and
+    /// thereby EIR.LINKIF.
+    pub fn take_link_change(&mut self) -> Result<bool, Error<SPI::Error>> {
+        let phir = Phir::from(self.read_phy(PHIR)?);
+        Ok(phir.contains(Phir::PLNKIF))
+    }
+
+    /// Configures the functions of the LEDA and LEDB pins.
+    ///
+    /// Stretchable events are stretched to the normal duration of about 40 ms, as in the power-on
+    /// default.
+    pub fn set_led_mode(&mut self, leda: LedMode, ledb: LedMode) -> Result<(), Error<SPI::Error>> {
+        // LFRQ = 00 (normal stretch), STRCH = 1 (stretch events).
+        let phlcon = (PhLcon::RESERVED | PhLcon::STRCH).with_leds(leda, ledb);
+        self.write_phy(PHLCON, phlcon.bits())
+    }
+
+    /// Enables PHY loopback, so that transmitted frames are returned to the MAC instead of being
+    /// sent onto the network. This allows the transmit and receive paths to be tested without a
+    /// cable.
+    ///
+    /// Loopback only works in full-duplex mode, so the driver must have been initialized with
+    /// [`Duplex::Full`]; otherwise this fails with [`Error::RequiresFullDuplex`] without touching
+    /// the device. The receive filters still apply to looped back frames, which should
+    /// therefore be sent to the broadcast or station MAC address, unless the filters are disabled
+    /// with [`Enc28j60::set_promiscuous`].
+    pub fn enable_loopback(&mut self) -> Result<(), Error<SPI::Error>> {
+        if self.duplex != Duplex::Full {
+            return Err(Error::RequiresFullDuplex);
+        }
+
+        // Clear PHCON2.HDLDIS and set PHCON2.FRCLNK, so that the link is up without a partner.
+        self.write_phy(PHCON2, PhCon2::FRCLNK.bits())?;
+
+        let mut phcon1 = PhCon1::from(self.read_phy(PHCON1)?);
+        phcon1.insert(PhCon1::PLOOPBK);
+        self.write_phy(PHCON1, phcon1.bits())
+    }
+
+    /// Disables PHY loopback enabled with [`Enc28j60::enable_loopback`].
+    pub fn disable_loopback(&mut self) -> Result<(), Error<SPI::Error>> {
+        let mut phcon1 = PhCon1::from(self.read_phy(PHCON1)?);
+        phcon1.remove(PhCon1::PLOOPBK);
+        self.write_phy(PHCON1, phcon1.bits())?;
+
+        // Restore PHCON2 as programmed by `initialize`.
+        self.write_phy(PHCON2, PhCon2::HDLDIS.bits())
+    }
+
+    //
+    // Network function
+    //
+
+    /// Returns the number of packets waiting in the receive buffer (EPKTCNT).
+    pub fn pending_packets(&mut self) -> Result<u8, Error<SPI::Error>> {
+        self.read_control(EPKTCNT)
+    }
+
+    /// Receive a single packet into `buf`, truncating it if `buf` is too small.
+    ///
+    /// Returns the number of bytes written into `buf` along with the length and receive status
+    /// of the packet, or `None` if no packet is pending. A packet holding nothing but its CRC is
+    /// returned with a length of zero. Fails with
+    /// [`Error::NotInitialized`] if [`Enc28j60::initialize`] has not been called, and with
+    /// [`Error::RuntFrame`] if the packet is too short to hold even a CRC, in which case it is
+    /// dropped and the next call moves on to the following packet.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<Option<ReceivedPacket>, Error<SPI::Error>> {
+        let Some((header, packet)) = self.peek_packet(buf)? else {
+            return Ok(None);
+        };
+
+        // The rest of a truncated packet need not be skipped, since ERDPT is set from the next
+        // packet pointer on each receive.
+        self.release_packet(&header)?;
+        Ok(Some(packet))
+    }
+
+    /// Like [`Enc28j60::receive`], but leaves the packet in the receive buffer, so that the next
+    /// call to `peek` or `receive` reads it again.
+    ///
+    /// Use [`Enc28j60::consume`] to drop the packet once it has been inspected. Until then, its
+    /// memory is not freed for new packets. A receive buffer overflow is only reported once,
+    /// by whichever call noticed it first.
+    pub fn peek(&mut self, buf: &mut [u8]) -> Result<Option<ReceivedPacket>, Error<SPI::Error>> {
+        Ok(self.peek_packet(buf)?.map(|(_, packet)| packet))
+    }
+
+    /// Like [`Enc28j60::peek`], but also returns the header of the packet, so that it can be
+    /// released with [`Enc28j60::release_packet`] without reading the header again.
+    pub(crate) fn peek_packet(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<(ReceiveHeader, ReceivedPacket)>, Error<SPI::Error>> {
+        let Some(header) = self.read_receive_header()? else {
+            return Ok(None);
+        };
+
+        // Read the packet payload into the buffer
+        let copy_len = min(header.len, buf.len());
+        if copy_len > 0 {
+            self.mem_read(&mut buf[..copy_len])?;
+        }
+
+        let packet = header.packet(copy_len);
+        Ok(Some((header, packet)))
+    }
+
+    /// Drops the next pending packet without reading it, typically after inspecting it with
+    /// [`Enc28j60::peek`]. Does nothing if no packet is pending.
+    pub fn consume(&mut self) -> Result<(), Error<SPI::Error>> {
+        if let Some(header) = self.read_receive_header()? {
+            self.release_packet(&header)?;
+        }
+        Ok(())
+    }
+
+    /// Receives every pending packet, passing each to `f` along with its full length.
+    ///
+    /// Packets are read into a scratch buffer on the stack which holds a frame of
+    /// [`DEFAULT_MAX_FRAME_LENGTH`]. Should a longer packet be received, for example after raising
+    /// the limit with [`Enc28j60::set_max_frame_length`], the slice passed to `f` is truncated and
+    /// shorter than the length. Runt frames are released and skipped without calling `f`. Returns
+    /// the number of packets received.
+    pub fn receive_all(
+        &mut self,
+        mut f: impl FnMut(&[u8], usize),
+    ) -> Result<usize, Error<SPI::Error>> {
+        let mut scratch = [0u8; DEFAULT_MAX_FRAME_LENGTH as usize];
+        let mut count = 0;
+        loop {
+            match self.receive(&mut scratch) {
+                Ok(Some(packet)) => {
+                    f(&scratch[..packet.copied], packet.total);
+                    count += 1;
+                }
+                Ok(None) => return Ok(count),
+                // The runt has already been released, so the next packet can be read.
+                Err(Error::RuntFrame) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Receives a single packet without buffering it, passing its contents to `sink` in chunks of
+    /// up to 64 bytes as they are read from the device.
+    ///
+    /// Returns the length and receive status of the packet, as [`Enc28j60::receive`] would with a
+    /// buffer large enough to hold it, or `None`, without calling `sink`, if no packet is
+    /// pending.
+    pub fn receive_streaming(
+        &mut self,
+        mut sink: impl FnMut(&[u8]),
+    ) -> Result<Option<ReceivedPacket>, Error<SPI::Error>> {
+        let Some(header) = self.read_receive_header()? else {
+            return Ok(None);
+        };
+
+        let mut remaining = header.len;
+        let mut chunk = [0u8; 64];
+        while remaining > 0 {
+            let chunk_size = min(remaining, chunk.len());
+            self.mem_read(&mut chunk[..chunk_size])?;
+            sink(&chunk[..chunk_size]);
+            remaining -= chunk_size;
+        }
+
+        self.release_packet(&header)?;
+
+        Ok(Some(ReceivedPacket {
+            copied: header.len,
+            total: header.len,
+            status: header.status,
+            overflowed: header.overflowed,
+        }))
+    }
+
+    /// Returns an iterator over the pending packets, which receives them one by one until none is
+    /// left.
+    ///
+    /// ```ignore
+    /// for frame in enc.frames().take(4) {
+    ///     let frame = frame?;
+    ///     if frame.status().received_ok() {
+    ///         handle(frame.data());
+    ///     }
+    /// }
+    /// ```
+    pub fn frames(&mut self) -> FrameIter<'_, SPI, INT, RST, DELAY> {
+        FrameIter::new(self)
+    }
+
+    /// Receives a single packet into a newly allocated vector of exactly its length, excluding
+    /// the CRC. Returns `None` if no packet is pending.
+    ///
+    /// Unlike [`Enc28j60::receive`], the receive status is not returned, so frames with an invalid
+    /// CRC are only kept out by [`ReceiveFilter::CRC_CHECK`].
+    #[cfg(feature = "alloc")]
+    pub fn receive_vec(&mut self) -> Result<Option<Vec<u8>>, Error<SPI::Error>> {
+        let Some(header) = self.read_receive_header()? else {
+            return Ok(None);
+        };
+
+        let mut frame = vec![0u8; header.len];
+        if !frame.is_empty() {
+            self.mem_read(&mut frame)?;
+        }
+
+        self.release_packet(&header)?;
+        Ok(Some(frame))
+    }
+
+    /// Reads the receive status vector of the next packet, leaving ERDPT at the start of the
+    /// frame. Returns `None` if no packet is pending.
+    fn read_receive_header(&mut self) -> Result<Option<ReceiveHeader>, Error<SPI::Error>> {
+        if !self.initialized {
+            return Err(Error::NotInitialized);
+        }
+
+        if self.pending_packets()? == 0 {
+            return Ok(None);
+        }
+
+        let overflowed = self.rx_overflow()?;
+
+        // Start reading from the beginning of the next Packet Pointer
+        self.write_u16(ERDPTL, ERDPTH, self.next_packet)?;
+
+        // Read the receive status vector (6 bytes)
+        // Format: [next_packet_ptr(2), byte_count(2), status(2)]
+        let mut rsv = [0u8; 6];
+        self.mem_read(&mut rsv)?;
+
+        let Some(header) = ReceiveHeader::parse(rsv, overflowed, self.rx_start..=self.rx_end)
+        else {
+            self.reset_receive_logic()?;
+            return Err(Error::CorruptReceiveBuffer);
+        };
+
+        if header.runt {
+            self.release_packet(&header)?;
+            return Err(Error::RuntFrame);
+        }
+        Ok(Some(header))
+    }
+
+    /// Resets the receive logic and empties the receive buffer, after its contents can no longer
+    /// be trusted.
+    fn reset_receive_logic(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.run(sequence::reset_receive())?;
+        for _ in 0..self.pending_packets()? {
+            self.run([sequence::decrement_packet_count()])?;
+        }
+        self.run(sequence::restart_receive(self.rx_start))?;
+        self.next_packet = self.rx_start;
+        Ok(())
+    }
+
+    /// Frees the memory of the packet just read and moves on to the packet after it, counting it
+    /// in the statistics.
+    pub(crate) fn release_packet(
+        &mut self,
+        header: &ReceiveHeader,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.stats.count_received(header.status);
+
+        // From data sheet: "The host controller will save the next Packet Pointer ..."
+        self.next_packet = header.next_packet;
+
+        // Update ERXRDPT to free the memory used by this packet
+        self.run(sequence::release_packet(
+            header.next_packet,
+            self.rx_start,
+            self.rx_end,
+        ))
+    }
+
+    /// Transmit a packet with the given source MAC, destination MAC, EtherType, and data payload.
+    /// The EtherType is written after the source MAC, so `data` holds the payload only.
+    ///
+    /// Returns the transmit status vector reported by the device for the frame. If the device
+    /// aborts the transmission, it is retried up to the number of times set with
+    /// [`Enc28j60::set_transmit_retries`] before failing with [`Error::Aborted`], or with
+    /// [`Error::LateCollision`] or [`Error::ExcessiveCollisions`] if the last attempt was aborted
+    /// by collisions. Fails with [`Error::NotInitialized`] if [`Enc28j60::initialize`] has not
+    /// been called, and with [`Error::Busy`] if a transmission which timed out or was started with
+    /// [`Enc28j60::start_transmit`] has not completed yet.
+    pub fn transmit(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        ether_type: u16,
+        data: &[u8],
+    ) -> Result<TxStatus, Error<SPI::Error>> {
+        self.transmit_with(dst, src, ether_type, data, TransmitOptions::default())
+    }
+
+    /// Like [`Enc28j60::transmit`], but with per-frame control over padding and CRC generation.
+    pub fn transmit_with(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        ether_type: u16,
+        data: &[u8],
+        options: TransmitOptions,
+    ) -> Result<TxStatus, Error<SPI::Error>> {
+        self.start_transmit(dst, src, ether_type, data, options)?;
+        self.finish_transmit()
+    }
+
+    /// Like [`Enc28j60::transmit`], but the payload is the concatenation of `segments`.
+    ///
+    /// Each segment is written to the transmit buffer in turn, so a frame assembled from several
+    /// buffers can be sent without first copying it into a contiguous one.
+    pub fn transmit_vectored(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        ether_type: u16,
+        segments: &[&[u8]],
+    ) -> Result<TxStatus, Error<SPI::Error>> {
+        let options = TransmitOptions::default();
+        self.start_transmit_segments(dst, src, ether_type, segments, options)?;
+        self.finish_transmit()
+    }
+
+    /// Like [`Enc28j60::transmit`], but inserts an 802.1Q tag with the priority code point `pcp`
+    /// and the VLAN identifier `vlan_id` ahead of `ether_type`.
+    ///
+    /// Tagged frames may exceed the maximum frame length by the 4 bytes of the tag, so with the
+    /// default limit they may be up to 1522 bytes long.
+    pub fn transmit_vlan(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        vlan_id: u16,
+        pcp: u8,
+        ether_type: u16,
+        data: &[u8],
+    ) -> Result<TxStatus, Error<SPI::Error>> {
+        const TPID: u16 = 0x8100;
+        const TAG_LEN: usize = 4;
+        const CRC_LEN: usize = 4;
+
+        let frame_len = dst.len() + src.len() + 2 + TAG_LEN + data.len();
+        if frame_len + CRC_LEN > usize::from(self.max_frame_len) + TAG_LEN {
+            return Err(Error::FrameTooLong);
+        }
+
+        // The tag control information holds the PCP, DEI and VID, in that order. The tag is
+        // followed by the EtherType of the payload.
+        let tci = (u16::from(pcp & 0b111) << 13) | (vlan_id & 0x0fff);
+        let [tci_hi, tci_lo] = tci.to_be_bytes();
+        let [type_hi, type_lo] = ether_type.to_be_bytes();
+        let tag = [tci_hi, tci_lo, type_hi, type_lo];
+
+        // Without PHUGEEN, the MAC would abort frames the tag makes longer than MAMXFL.
+        let options = TransmitOptions {
+            override_defaults: true,
+            append_crc: true,
+            pad: true,
+            allow_huge: true,
+        };
+        self.start_transmit_segments(dst, src, TPID, &[&tag, data], options)?;
+        self.finish_transmit()
+    }
+
+    /// Transmits an 802.3x PAUSE frame, asking the link partner to stop transmitting for `quanta`
+    /// times 512 bit times. A `quanta` of 0 asks it to resume immediately.
+    ///
+    /// The frame is sent from the station MAC address. PAUSE frames are only honored on
+    /// full-duplex links. To have the device send them by itself, see
+    /// [`Enc28j60::start_flow_control`].
+    pub fn send_pause(&mut self, quanta: u16) -> Result<TxStatus, Error<SPI::Error>> {
+        const PAUSE_ADDRESS: [u8; 6] = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x01];
+        const MAC_CONTROL: u16 = 0x8808;
+        const PAUSE_OPCODE: u16 = 0x0001;
+
+        let src = self.mac_address;
+        let [opcode_hi, opcode_lo] = PAUSE_OPCODE.to_be_bytes();
+        let [quanta_hi, quanta_lo] = quanta.to_be_bytes();
+        let data = [opcode_hi, opcode_lo, quanta_hi, quanta_lo];
+        // The MAC pads the frame to the minimum length.
+        self.transmit(&PAUSE_ADDRESS, &src, MAC_CONTROL, &data)
+    }
+
+    /// Starts hardware flow control: in full-duplex mode, the device transmits PAUSE frames with
+    /// a pause time of `quanta` periodically until [`Enc28j60::stop_flow_control`] is called. In
+    /// half-duplex mode, it applies backpressure by jamming the link instead, and `quanta` is
+    /// ignored.
+    ///
+    /// This is typically started when the receive buffer is close to full, and stopped once the
+    /// pending packets have been read.
+    pub fn start_flow_control(&mut self, quanta: u16) -> Result<(), Error<SPI::Error>> {
+        let fcen = match self.duplex {
+            Duplex::Full => {
+                self.write_u16(EPAUSL, EPAUSH, quanta)?;
+
+                // MACON1.TXPAUS allows the MAC to transmit PAUSE frames.
+                let macon1: Macon1 = self.read_reg(MACON1)?;
+                self.write_control(MACON1, (macon1 | Macon1::TXPAUS).bits())?;
+
+                // EFLOCON.FCEN = 10: send PAUSE frames periodically.
+                0b10
+            }
+            // EFLOCON.FCEN0 = 1: turn backpressure on. FCEN1 is ignored in half-duplex mode.
+            Duplex::Half => 0b01,
+        };
+        self.write_control(EFLOCON, fcen)
+    }
+
+    /// Stops hardware flow control started with [`Enc28j60::start_flow_control`]. In full-duplex
+    /// mode, a final PAUSE frame with a pause time of 0 is sent so that the link partner resumes
+    /// immediately.
+    pub fn stop_flow_control(&mut self) -> Result<(), Error<SPI::Error>> {
+        let fcen = match self.duplex {
+            // EFLOCON.FCEN = 11: send one PAUSE frame with a zero pause time, then turn flow
+            // control off.
+            Duplex::Full => 0b11,
+            // EFLOCON.FCEN0 = 0: turn backpressure off.
+            Duplex::Half => 0b00,
+        };
+        self.write_control(EFLOCON, fcen)
+    }
+
+    /// Waits for the transmission in progress to complete, retrying it if it is aborted.
+    fn finish_transmit(&mut self) -> Result<TxStatus, Error<SPI::Error>> {
+        let mut retry = TransmitRetry::new(self.tx_retries, self.poll_limit);
+        loop {
+            match retry.next(self.poll_transmit()?) {
+                TransmitAction::Poll => {}
+                TransmitAction::Retry => {
+                    self.run(sequence::reset_transmit())?;
+                    self.arm_transmit()?;
+                }
+                TransmitAction::Finish(result) => return result,
+            }
+        }
+    }
+
+    /// Writes a frame into the transmit buffer and starts transmitting it, without waiting for
+    /// the transmission to complete.
+    ///
+    /// Use [`Enc28j60::poll_transmit`] to find out when the transmission completes. Until it
+    /// reports the previous transmission as done or aborted, starting another frame fails with
+    /// [`Error::Busy`]. Aborted transmissions are not retried.
+    pub fn start_transmit(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        ether_type: u16,
+        data: &[u8],
+        options: TransmitOptions,
+    ) -> Result<(), Error<SPI::Error>> {
+        self.start_transmit_segments(dst, src, ether_type, &[data], options)
+    }
+
+    /// Writes a frame with the concatenation of `segments` as payload into the transmit buffer and
+    /// starts transmitting it.
+    fn start_transmit_segments(
+        &mut self,
+        dst: &[u8; 6],
+        src: &[u8; 6],
+        ether_type: u16,
+        segments: &[&[u8]],
+        options: TransmitOptions,
+    ) -> Result<(), Error<SPI::Error>> {
+        if !self.initialized {
+            return Err(Error::NotInitialized);
+        }
+
+        let data_len: usize = segments.iter().map(|segment| segment.len()).sum();
+        let frame_len = dst.len() + src.len() + 2 + data_len;
+        sequence::check_frame_length(frame_len, self.max_frame_len, &options)?;
+
+        // Writing the buffer would corrupt the frame still in flight, and reprogramming ETXND
+        // would make `poll_transmit` read the wrong transmit status vector.
+        if self.tx_pending || self.read_reg::<Econ1>(ECON1)?.contains(Econ1::TXRTS) {
+            return Err(Error::Busy);
+        }
+
+        // 0. Reset the transmit logic (errata workaround, see `set_transmit_reset`)
+        if self.reset_tx_logic {
+            self.run(sequence::reset_transmit())?;
+        }
+
+        // 1a. Read current ETXST to know where to write
+        let tx_start = self.read_u16(ETXSTL, ETXSTH)?;
+        let tx_end = sequence::transmit_end(tx_start, frame_len)?;
+
+        // 1b. Set up write pointer to tx_start
+        self.write_u16(EWRPTL, EWRPTH, tx_start)?;
+
+        // 2a. Write the per-packet control byte
+        let control = [options.control_byte()];
+        self.mem_write(&control)?;
+
+        // 2b. Write the Ethernet frame header
+        self.mem_write(dst)?;
+        self.mem_write(src)?;
+        self.mem_write(&ether_type.to_be_bytes())?;
+
+        // 2c. Write the data
+        for segment in segments {
+            self.mem_write(segment)?;
+        }
+
+        // 3. Appropriately program the ETXND Pointer.
+        // It should point to the last byte in the data payload.
+        self.write_u16(ETXNDL, ETXNDH, tx_end)?;
+
+        self.arm_transmit()
+    }
+
+    /// Checks on a transmission started with [`Enc28j60::start_transmit`].
+    ///
+    /// Once the transmission is done, the transmit status vector is read back. The transmission
+    /// counts as aborted if ESTAT.TXABRT is set, or if the status vector reports a late or
+    /// excessive collision; ESTAT.TXABRT, ESTAT.LATECOL and EIR.TXERIF are then cleared.
+    pub fn poll_transmit(&mut self) -> Result<TxState, Error<SPI::Error>> {
+        if self.read_reg::<Econ1>(ECON1)?.contains(Econ1::TXRTS) {
+            return Ok(TxState::InProgress);
+        }
+
+        // Only count the completion once, however often it is polled.
+        let first_report = core::mem::take(&mut self.tx_pending);
+        let estat = self.read_estat()?;
+
+        // The device writes the transmit status vector just past ETXND, for aborted
+        // transmissions too.
+        let tx_end = self.read_u16(ETXNDL, ETXNDH)?;
+        self.write_u16(ERDPTL, ERDPTH, tx_end + 1)?;
+        let mut tsv = [0u8; TxStatus::LEN];
+        self.mem_read(&mut tsv)?;
+        let state = sequence::transmit_state(estat, TxStatus::from_bytes(tsv));
+
+        if let TxState::Aborted(_) = state {
+            self.run(sequence::clear_transmit_abort())?;
+        }
+        if first_report {
+            self.stats.count_transmit_state(&state);
+        }
+        Ok(state)
+    }
+
+    //
+    // Helper function
+    //
+
+    /// Starts transmitting the frame between ETXST and ETXND.
+    fn arm_transmit(&mut self) -> Result<(), Error<SPI::Error>> {
+        self.run(sequence::arm_transmit())?;
+        self.tx_pending = true;
+        Ok(())
+    }
+}
+
+/// The settings of the pattern match filter.
+#[derive(Clone, Copy)]
+struct PatternMatch {
+    /// The start of the window, as programmed into EPMO,
+    offset: u16,
+    /// The bytes of the window to match, as programmed into EPMM0-EPMM7,
+    mask: u64,
+    /// The checksum of the matched bytes, as programmed into EPMCS,
+    checksum: u16,
+}
+
+/// The receive status vector of a pending packet.
+pub(crate) struct ReceiveHeader {
+    /// The address of the packet after this one,
+    pub(crate) next_packet: u16,
+    /// The length of the frame, excluding the CRC,
+    pub(crate) len: usize,
+    /// Whether the frame is too short to hold even a CRC, in which case `len` is zero,
+    pub(crate) runt: bool,
+    /// The receive status,
+    pub(crate) status: RxStatus,
+    /// Whether the receive buffer overflowed before the packet was read,
+    pub(crate) overflowed: bool,
+}
+
+impl ReceiveHeader {
+    /// Decodes the receive status vector `rsv`, which holds the next packet pointer, the byte
+    /// count and the receive status, each in little-endian order.
+    ///
+    /// Returns `None` if the next packet pointer lies outside `rx_buffer`. Following it would free,
+    /// and later read, memory which belongs to the transmit buffer, so the receive logic must be
+    /// reset instead.
+    pub(crate) fn parse(
+        rsv: [u8; 6],
+        overflowed: bool,
+        rx_buffer: RangeInclusive<u16>,
+    ) -> Option<Self> {
+        let next_packet = u16::from_le_bytes([rsv[0], rsv[1]]);
+        let byte_count = usize::from(u16::from_le_bytes([rsv[2], rsv[3]]));
+        let status = RxStatus::from_bits(u16::from_le_bytes([rsv[4], rsv[5]]));
+
+        if !rx_buffer.contains(&next_packet) {
+            return None;
+        }
+
+        // The byte count includes the 4-byte CRC, so subtract it for payload length
+        let len = byte_count.checked_sub(4);
+        Some(ReceiveHeader {
+            next_packet,
+            len: len.unwrap_or(0),
+            runt: len.is_none(),
+            status,
+            overflowed,
+        })
+    }
+
+    /// Describes the packet, of which `copied` bytes were read.
+    pub(crate) fn packet(&self, copied: usize) -> ReceivedPacket {
+        ReceivedPacket {
+            copied,
+            total: self.len,
+            status: self.status,
+            overflowed: self.overflowed,
+        }
+    }
+}
+
+/// Computes the ERXRDPT value that frees the receive buffer up to `next_packet`, i.e. the byte
+/// before `next_packet` in the circular buffer spanning `rx_start..=rx_end`.
+///
+/// Silicon errata issue 14: the receive hardware may corrupt the circular receive buffer when an
+/// even value is programmed into ERXRDPT. The next packet pointer is always even due to hardware
+/// padding and ERXND is odd, so the result is normally odd already. Should it be even anyway, the
+/// pointer is moved back one more byte, which frees slightly less memory but is always safe.
+pub(crate) fn receive_read_pointer(next_packet: u16, rx_start: u16, rx_end: u16) -> u16 {
+    let before = |ptr: u16| match ptr.checked_sub(1) {
+        // Normal case: point to the byte before `ptr`
+        Some(prev) if prev >= rx_start => prev,
+        // Wrap-around case: `ptr` is at the start, so point to the end
+        _ => rx_end,
+    };
+
+    let rdpt = before(next_packet);
+    if rdpt % 2 == 0 { before(rdpt) } else { rdpt }
+}
+
+/// Returns the ECON1 bit field clear and set commands which select `bank`, when `current` is the
+/// bank selected now or `None` if it is unknown.
+///
+/// Only the bank select bits which differ are cleared or set, and a command with nothing to change
+/// is left out.
+pub(crate) fn bank_switch_commands(current: Option<Bank>, bank: Bank) -> [Option<[u8; 2]>; 2] {
+    let target = Econ1::bank_select(bank).bits();
+    let (clear, set) = match current {
+        Some(current) => {
+            let current = Econ1::bank_select(current).bits();
+            (current & !target, target & !current)
+        }
+        // Either bit may be wrong, so drive both.
+        None => (Econ1::BSEL.bits() & !target, target),
+    };
+
+    [
+        (clear != 0).then_some([ECON1.opcode(Op::BFC), clear]),
+        (set != 0).then_some([ECON1.opcode(Op::BFS), set]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::mock::{EREVID_B7, MockError, MockPin, MockSpi, NoDelay, driver, ready_driver};
+
+    fn wcr(reg: ControlRegister, data: u8) -> Vec<u8> {
+        vec![reg.opcode(Op::WCR), data]
+    }
+
+    fn rcr(reg: ControlRegister) -> Vec<u8> {
+        vec![reg.opcode(Op::RCR), 0]
+    }
+
+    /// Returns the commands switching from bank `from` to bank `to`.
+    fn bank_switch(from: Bank, to: Bank) -> Vec<Vec<u8>> {
+        let (from, to) = (from as u8, to as u8);
+        let mut commands = Vec::new();
+        if from & !to != 0 {
+            commands.push(vec![ECON1.opcode(Op::BFC), from & !to]);
+        }
+        if to & !from != 0 {
+            commands.push(vec![ECON1.opcode(Op::BFS), to & !from]);
+        }
+        commands
+    }
+
+    #[test]
+    fn global_registers_do_not_switch_banks() {
+        let (mut enc, spi) = driver();
+        enc.read_control(ESTAT).unwrap();
+        assert_eq!(spi.take_written(), [rcr(ESTAT)]);
+
+        enc.read_control(EREVID).unwrap();
+        spi.take_written();
+
+        enc.read_control(ESTAT).unwrap();
+        enc.write_control(ECON1, 0).unwrap();
+        assert_eq!(spi.take_written(), [rcr(ESTAT), wcr(ECON1, 0)]);
+    }
+
+    #[test]
+    fn banked_registers_switch_banks_once() {
+        let (mut enc, spi) = driver();
+        enc.read_control(ERDPTL).unwrap();
+        enc.read_control(EREVID).unwrap();
+        enc.read_control(EREVID).unwrap();
+
+        let mut expected = vec![rcr(ERDPTL)];
+        expected.extend(bank_switch(Bank::Bank0, Bank::Bank3));
+        expected.extend([rcr(EREVID), rcr(EREVID)]);
+        assert_eq!(spi.take_written(), expected);
+    }
+
+    #[test]
+    fn bank_switches_only_change_differing_bits() {
+        let (mut enc, spi) = driver();
+        enc.set_bank(Bank::Bank3).unwrap();
+        enc.set_bank(Bank::Bank1).unwrap();
+        enc.set_bank(Bank::Bank2).unwrap();
+        let bfc = ECON1.opcode(Op::BFC);
+        let bfs = ECON1.opcode(Op::BFS);
+        let expected = [
+            vec![bfs, 0b11],
+            vec![bfc, 0b10],
+            vec![bfc, 0b01],
+            vec![bfs, 0b10],
+        ];
+        assert_eq!(spi.take_written(), expected);
+    }
+
+    #[test]
+    fn reset_selects_bank_zero() {
+        let (mut enc, spi) = driver();
+        enc.set_bank(Bank::Bank3).unwrap();
+        enc.reset_via_spi(&mut NoDelay).unwrap();
+        spi.take_written();
+
+        enc.read_control(ERDPTL).unwrap();
+        assert_eq!(spi.take_written(), [rcr(ERDPTL)]);
+    }
+
+    #[test]
+    fn reset_waits_for_clkrdy() {
+        let (mut enc, spi) = driver();
+        // ESTAT before and after CLKRDY is set
+        spi.respond(&[0, 0, 0, 0, 0b1, 0]);
+        enc.reset(&mut NoDelay).unwrap();
+        assert_eq!(spi.take_written(), [rcr(ESTAT), rcr(ESTAT)]);
+
+        enc.set_poll_limit(3);
+        assert_eq!(enc.reset(&mut NoDelay), Err(Error::Timeout));
+        assert_eq!(spi.take_written(), [rcr(ESTAT), rcr(ESTAT), rcr(ESTAT)]);
+    }
+
+    #[test]
+    fn stored_delay_is_used_and_kept() {
+        /// A delay which adds up the time it was asked to wait.
+        struct TotalDelay(u64);
+
+        impl DelayNs for TotalDelay {
+            fn delay_ns(&mut self, ns: u32) {
+                self.0 += u64::from(ns);
+            }
+        }
+
+        let spi = MockSpi::default();
+        let pin = MockPin::default();
+        let mut enc = Enc28j60::new_with_delay(spi.clone(), pin.clone(), pin, TotalDelay(0));
+        // ESTAT with CLKRDY set
+        spi.respond(&[0, 0b1, 0]);
+        enc.reset_with_stored_delay().unwrap();
+        assert_eq!(enc.delay.as_ref().unwrap().0, 50_400);
+
+        spi.respond(&EREVID_B7);
+        let enc = enc
+            .initialize_with_stored_delay(&DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        assert_eq!(enc.delay.as_ref().unwrap().0, 1_050_400);
+    }
+
+    #[test]
+    fn failed_transfer_forces_bank_select() {
+        let (mut enc, spi) = driver();
+        enc.read_control(MACON1).unwrap();
+        spi.take_written();
+
+        spi.fail_next();
+        assert_eq!(enc.read_control(MACON3), Err(Error::Spi(MockError)));
+        enc.read_control(MACON3).unwrap();
+        assert_eq!(
+            spi.take_written(),
+            [
+                rcr(MACON3),
+                vec![ECON1.opcode(Op::BFC), 0b01],
+                vec![ECON1.opcode(Op::BFS), 0b10],
+                rcr(MACON3),
+            ]
+        );
+
+        enc.invalidate_bank_cache();
+        enc.read_control(ERDPTL).unwrap();
+        assert_eq!(
+            spi.take_written(),
+            [vec![ECON1.opcode(Op::BFC), 0b11], rcr(ERDPTL)]
+        );
+
+        // Buffer memory transfers invalidate the cache as well.
+        spi.fail_next();
+        assert_eq!(enc.mem_write(&[0]), Err(Error::Spi(MockError)));
+        enc.read_control(ERDPTL).unwrap();
+        let written = spi.take_written();
+        assert_eq!(
+            written[1..],
+            [vec![ECON1.opcode(Op::BFC), 0b11], rcr(ERDPTL)]
+        );
+    }
+
+    #[test]
+    fn mac_and_mii_reads_skip_dummy_byte() {
+        let (mut enc, spi) = driver();
+        enc.set_bank(Bank::Bank2).unwrap();
+        spi.respond(&[0xaa, 0xbb, 0x0d]);
+        assert_eq!(enc.read_control(MACON1), Ok(0x0d));
+    }
+
+    #[test]
+    fn eth_reads_take_first_data_byte() {
+        let (mut enc, spi) = driver();
+        spi.respond(&[0xaa, 0x0d, 0xbb]);
+        assert_eq!(enc.read_control(ERDPTL), Ok(0x0d));
+    }
+
+    #[test]
+    fn link_down_latched_reads_llstat() {
+        let (mut enc, spi) = ready_driver();
+        // MISTAT, MIRDL and MIRDH, with LLSTAT latched low after a loss of link
+        spi.respond(&[0, 0, 0, 0, 0, 0b000, 0, 0, 0]);
+        assert_eq!(enc.link_down_latched(), Ok(true));
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(MIREGADR, PHSTAT1.addr())));
+
+        // Reading PHSTAT1 released the latch, so LLSTAT follows the link again.
+        spi.respond(&[0, 0, 0, 0, 0, 0b100, 0, 0, 0]);
+        assert_eq!(enc.link_down_latched(), Ok(false));
+    }
+
+    #[test]
+    fn link_interrupt_uses_phie_and_phir() {
+        let (mut enc, spi) = ready_driver();
+        enc.enable_link_interrupt().unwrap();
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(MIREGADR, PHIE.addr())));
+        assert!(written.contains(&wcr(MIWRL, 0x12)));
+
+        // MISTAT, MIRDL and MIRDH, with PLNKIF and PGIF set
+        spi.respond(&[0, 0, 0, 0, 0, 0x14, 0, 0, 0]);
+        assert_eq!(enc.take_link_change(), Ok(true));
+        assert!(spi.take_written().contains(&wcr(MIREGADR, PHIR.addr())));
+
+        // PGIF alone does not report a link change.
+        spi.respond(&[0, 0, 0, 0, 0, 0x04, 0, 0, 0]);
+        assert_eq!(enc.take_link_change(), Ok(false));
+    }
+
+    #[test]
+    fn link_status_extracts_lstat_and_dpxstat() {
+        for (phstat2, up, full_duplex) in [
+            (0x0000, false, false),
+            (0x0400, true, false),
+            (0x0200, false, true),
+            (0x0600, true, true),
+            // Unrelated status bits are ignored.
+            (!0x0600, false, false),
+        ] {
+            let (mut enc, spi) = ready_driver();
+            let [low, high] = u16::to_le_bytes(phstat2);
+            // MISTAT not busy, MIRDL, MIRDH
+            spi.respond(&[0, 0, 0, 0, 0, low, 0, 0, high]);
+            assert_eq!(
+                enc.link_status(),
+                Ok(LinkStatus { up, full_duplex }),
+                "{phstat2:#06x}"
+            );
+            assert!(spi.take_written().contains(&wcr(MIREGADR, PHSTAT2.addr())));
+        }
+    }
+
+    #[test]
+    fn set_led_mode_composes_phlcon() {
+        for (leda, ledb, phlcon) in [
+            // Link status on LEDA, transmit and receive activity on LEDB
+            (LedMode::Link, LedMode::TransmitReceive, 0x3472u16),
+            (LedMode::LinkActivity, LedMode::Off, 0x3d92),
+            (LedMode::On, LedMode::BlinkSlow, 0x38b2),
+        ] {
+            let (mut enc, spi) = ready_driver();
+            enc.set_led_mode(leda, ledb).unwrap();
+            let [low, high] = phlcon.to_le_bytes();
+            let written = spi.take_written();
+            assert!(written.contains(&wcr(MIREGADR, PHLCON.addr())));
+            assert!(written.ends_with(&[wcr(MIWRL, low), wcr(MIWRH, high)]));
+        }
+    }
+
+    #[test]
+    fn read_phy_waits_for_busy_to_clear() {
+        let (mut enc, spi) = driver();
+        // MISTAT busy, then ready
+        spi.respond(&[0, 0, 0b01, 0, 0, 0b00]);
+        // MIRDL, MIRDH
+        spi.respond(&[0, 0, 0x34, 0, 0, 0x12]);
+        assert_eq!(enc.read_phy(PHCON2), Ok(0x1234));
+
+        let expected = [
+            bank_switch(Bank::Bank0, Bank::Bank2),
+            vec![wcr(MIREGADR, PHCON2.addr()), wcr(MICMD, 0b01)],
+            bank_switch(Bank::Bank2, Bank::Bank3),
+            vec![rcr(MISTAT), rcr(MISTAT)],
+            bank_switch(Bank::Bank3, Bank::Bank2),
+            vec![wcr(MICMD, 0b00), rcr(MIRDL), rcr(MIRDH)],
+        ]
+        .concat();
+        assert_eq!(spi.take_written(), expected);
+    }
+
+    #[test]
+    fn write_phy_does_not_poll_busy() {
+        let (mut enc, spi) = driver();
+        enc.write_phy(PHCON2, 0x1234).unwrap();
+
+        let mut expected = bank_switch(Bank::Bank0, Bank::Bank2);
+        expected.extend([
+            wcr(MIREGADR, PHCON2.addr()),
+            wcr(MIWRL, 0x34),
+            wcr(MIWRH, 0x12),
+        ]);
+        assert_eq!(spi.take_written(), expected);
+    }
+
+    /// Returns the PHY register writes in `written` as pairs of address and value.
+    fn phy_writes(written: &[Vec<u8>]) -> Vec<(u8, u16)> {
+        let mut writes = Vec::new();
+        let (mut addr, mut lo) = (0, 0);
+        for bytes in written {
+            if bytes[0] == MIREGADR.opcode(Op::WCR) {
+                addr = bytes[1];
+            } else if bytes[0] == MIWRL.opcode(Op::WCR) {
+                lo = bytes[1];
+            } else if bytes[0] == MIWRH.opcode(Op::WCR) {
+                writes.push((addr, u16::from_le_bytes([lo, bytes[1]])));
+            }
+        }
+        writes
+    }
+
+    #[test]
+    fn loopback_sets_and_clears_ploopbk() {
+        let (mut enc, spi) = ready_driver();
+        // PHCON1 with PDPXMD set: MISTAT, MIRDL, MIRDH
+        spi.respond(&[0, 0, 0, 0, 0, 0x00, 0, 0, 0x01]);
+        enc.enable_loopback().unwrap();
+        let writes = phy_writes(&spi.take_written());
+        assert_eq!(writes, [(PHCON2.addr(), 0x4000), (PHCON1.addr(), 0x4100)]);
+
+        spi.respond(&[0, 0, 0, 0, 0, 0x00, 0, 0, 0x41]);
+        enc.disable_loopback().unwrap();
+        let writes = phy_writes(&spi.take_written());
+        assert_eq!(writes, [(PHCON1.addr(), 0x0100), (PHCON2.addr(), 0x0100)]);
+    }
+
+    #[test]
+    fn loopback_requires_full_duplex() {
+        let (enc, spi) = driver();
+        spi.respond(&EREVID_B7);
+        let mut enc = enc
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Half)
+            .unwrap();
+        spi.take_written();
+        assert_eq!(enc.enable_loopback(), Err(Error::RequiresFullDuplex));
+        assert!(spi.take_written().is_empty());
+    }
+
+    #[test]
+    fn self_test_reports_results() {
+        let (mut enc, spi) = driver();
+        // EREVID, ESTAT
+        spi.respond(&[0, 0x06, 0, 0, 0x01, 0]);
+        // PHID1: MISTAT, MIRDL, MIRDH
+        spi.respond(&[0, 0, 0, 0, 0, 0x83, 0, 0, 0x00]);
+        // PHID2: MISTAT, MIRDL, MIRDH
+        spi.respond(&[0, 0, 0, 0, 0, 0x00, 0, 0, 0x14]);
+        // ECON1, ERXND
+        spi.respond(&[0, 0, 0, 0, 0xff, 0, 0, 0x0f, 0]);
+        // The saved contents, then the pattern read back
+        spi.respond(&[0; 8]);
+        spi.respond(&[0x00, 0xff, 0x55, 0xaa, 0x0f, 0xf0, 0x5a, 0xa5]);
+
+        let diagnostics = enc.self_test().unwrap();
+        assert_eq!(diagnostics.revision, Revision::B7);
+        assert_eq!(diagnostics.phy_id, (0x0083, 0x1400));
+        assert!(diagnostics.passed());
+    }
+
+    #[test]
+    fn memory_test_restores_contents() {
+        let (mut enc, spi) = driver();
+        // ECON1 with RXEN set, ERXND
+        spi.respond(&[0, 0b0000_0100, 0, 0, 0xff, 0, 0, 0x0f, 0]);
+        // The saved contents, then a corrupted read back
+        spi.respond(&[0x11, 0x22, 0x33, 0x44, 0xaa, 0x55, 0xaa, 0x54]);
+        assert_eq!(
+            enc.memory_test(0x1ffc, &[0xaa, 0x55, 0xaa, 0x55]),
+            Ok(false)
+        );
+
+        let written = spi.take_written();
+        let wbm = (Op::WBM as u8) | 0x1a;
+        let n = written.len();
+        assert_eq!(written[n - 2], [wbm, 0x11, 0x22, 0x33, 0x44]);
+        assert_eq!(written[n - 1], [ECON1.opcode(Op::BFS), 0b0000_0100]);
+    }
+
+    #[test]
+    fn memory_test_does_not_read_past_erxnd() {
+        let (mut enc, spi) = driver();
+        // ECON1, ERXND
+        spi.respond(&[0, 0, 0, 0, 0xff, 0, 0, 0x0f, 0]);
+        // Two chunks of two bytes: saved contents and read back each
+        spi.respond(&[0, 0, 1, 2, 0, 0, 3, 4]);
+        assert_eq!(enc.memory_test(0x0ffe, &[1, 2, 3, 4]), Ok(true));
+
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(ERDPTH, 0x0f)));
+        assert!(written.contains(&wcr(ERDPTH, 0x10)));
+    }
+
+    #[test]
+    fn max_frame_length_rejects_out_of_range() {
+        let (mut enc, spi) = driver();
+        assert_eq!(enc.set_max_frame_length(63), Err(Error::InvalidArgument));
+        assert_eq!(enc.set_max_frame_length(64), Ok(()));
+        assert_eq!(enc.set_max_frame_length(4090), Ok(()));
+        assert_eq!(enc.set_max_frame_length(4091), Err(Error::InvalidArgument));
+        assert_eq!(enc.set_max_frame_length(8000), Err(Error::InvalidArgument));
+        assert_eq!(enc.max_frame_length(), 4090);
+
+        let config = Enc28j60Config {
+            max_frame_length: 8000,
+            ..Enc28j60Config::default()
+        };
+        let error = enc
+            .initialize_with(&mut NoDelay, &config)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(error.error, Error::InvalidArgument);
+        assert!(spi.take_written().is_empty());
+    }
+
+    #[test]
+    fn initialize_programs_max_frame_length() {
+        let (mut enc, spi) = driver();
+        enc.set_max_frame_length(1522).unwrap();
+        spi.respond(&EREVID_B7);
+        let mut enc = enc
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(MAMXFLL, 0xf2)));
+        assert!(written.contains(&wcr(MAMXFLH, 0x05)));
+
+        let dst = [0xff; 6];
+        let result = enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 1505]);
+        assert_eq!(result, Err(Error::FrameTooLong));
+    }
+
+    #[test]
+    fn initialize_programs_inter_packet_gap() {
+        let (enc, spi) = driver();
+        spi.respond(&EREVID_B7);
+        enc.initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(MABBIPG, 0x15)));
+        assert!(written.contains(&wcr(MAIPGL, 0x12)));
+        assert!(
+            !written
+                .iter()
+                .any(|bytes| bytes[0] == MAIPGH.opcode(Op::WCR))
+        );
+
+        let (mut enc, spi) = driver();
+        enc.set_inter_packet_gap(InterPacketGap {
+            back_to_back: 0x20,
+            non_back_to_back: 0x21,
+            non_back_to_back_high: 0x0d,
+        });
+        spi.respond(&EREVID_B7);
+        enc.initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Half)
+            .unwrap();
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(MABBIPG, 0x20)));
+        assert!(written.contains(&wcr(MAIPGL, 0x21)));
+        assert!(written.contains(&wcr(MAIPGH, 0x0d)));
+    }
+
+    #[test]
+    fn initialize_with_applies_config() {
+        let (enc, spi) = driver();
+        let filter = ReceiveFilter::UNICAST | ReceiveFilter::CRC_CHECK;
+        let config = Enc28j60Config {
+            mac_address: [0x02, 0, 0, 0, 0, 1],
+            duplex: Duplex::Half,
+            receive_filter: filter,
+            max_frame_length: 1522,
+            macon4: Macon4::DEFER,
+            ..Enc28j60Config::default()
+        };
+        spi.respond(&EREVID_B7);
+        enc.initialize_with(&mut NoDelay, &config).unwrap();
+
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(ERXFCON, filter.bits())));
+        assert!(written.contains(&wcr(MAMXFLL, 0xf2)));
+        assert!(written.contains(&wcr(MACON4, 0b0100_0000)));
+        assert!(written.contains(&wcr(MAADR6, 0x01)));
+        // Half duplex leaves MACON3.FULDPX clear.
+        assert!(written.contains(&wcr(MACON3, 0b0011_0010)));
+    }
+
+    #[test]
+    fn into_inner_returns_peripherals() {
+        let (mut enc, spi) = ready_driver();
+        enc.reset_via_spi(&mut NoDelay).unwrap();
+        let (inner, _int, _reset) = enc.into_inner();
+        // The returned device is the one the driver was given.
+        assert_eq!(inner.take_written(), [vec![0xff]]);
+        assert!(spi.take_written().is_empty());
+    }
+
+    #[test]
+    fn resync_bank_reads_econ1() {
+        let (mut enc, spi) = driver();
+        // Select bank 2 behind the driver's back.
+        let cmd = [
+            ECON1.opcode(Op::BFS),
+            Econ1::bank_select(Bank::Bank2).bits(),
+        ];
+        enc.spi_mut().write(&cmd).unwrap();
+
+        spi.respond(&[0, 0b10]);
+        assert_eq!(enc.resync_bank(), Ok(Bank::Bank2));
+        enc.set_macon4(Macon4::empty()).unwrap();
+        assert_eq!(
+            spi.take_written(),
+            [cmd.to_vec(), rcr(ECON1), wcr(MACON4, 0)]
+        );
+    }
+
+    /// Returns the bytes written to the buffer memory in `written`.
+    fn buffer_writes(written: &[Vec<u8>]) -> Vec<u8> {
+        let wbm = (Op::WBM as u8) | 0x1a;
+        written
+            .iter()
+            .filter(|bytes| bytes[0] == wbm)
+            .flat_map(|bytes| bytes[1..].iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn receive_rejects_next_packet_outside_rx_buffer() {
+        let (mut enc, spi) = ready_driver();
+        // EPKTCNT, EIR
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        // Receive status vector pointing into the transmit buffer
+        spi.respond(&[0x34, 0x12, 0x44, 0x00, 0x80, 0x00]);
+        // EPKTCNT during the reset
+        spi.respond(&[0, 1, 0]);
+        assert_eq!(
+            enc.receive(&mut [0u8; 64]),
+            Err(Error::CorruptReceiveBuffer)
+        );
+
+        let written = spi.take_written();
+        assert!(written.contains(&vec![ECON1.opcode(Op::BFS), Econ1::RXRST.bits()]));
+        assert!(written.contains(&vec![ECON2.opcode(Op::BFS), Econ2::PKTDEC.bits()]));
+        assert!(written.contains(&wcr(ERXRDPTH, 0x00)));
+        assert!(!written.contains(&wcr(ERXRDPTH, 0x12)));
+        assert_eq!(
+            written.last(),
+            Some(&vec![ECON1.opcode(Op::BFS), Econ1::RXEN.bits()])
+        );
+        assert_eq!(enc.next_packet, 0);
+        assert_eq!(enc.stats().rx_frames, 0);
+    }
+
+    #[test]
+    fn truncated_receive_skips_rest_of_frame() {
+        let (mut enc, spi) = ready_driver();
+        // EPKTCNT, EIR
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        // Receive status vector of a 1518-byte frame, followed by the next packet at 0600h
+        spi.respond(&[0x00, 0x06, 0xee, 0x05, 0x80, 0x00]);
+        let packet = enc.receive(&mut [0u8; 16]).unwrap().unwrap();
+        assert_eq!((packet.copied, packet.total), (16, 1514));
+
+        let written = spi.take_written();
+        let rbm = (Op::RBM as u8) | 0x1a;
+        assert_eq!(written.iter().filter(|bytes| bytes[0] == rbm).count(), 2);
+        assert!(written.ends_with(&[
+            wcr(ERXRDPTL, 0xff),
+            wcr(ERXRDPTH, 0x05),
+            vec![ECON2.opcode(Op::BFS), Econ2::PKTDEC.bits()],
+        ]));
+        assert_eq!(enc.next_packet, 0x0600);
+    }
+
+    #[test]
+    fn peek_leaves_packet_pending() {
+        let (mut enc, spi) = ready_driver();
+        for _ in 0..2 {
+            // EPKTCNT, EIR
+            spi.respond(&[0, 1, 0, 0, 0, 0]);
+            // Receive status vector of a 4-byte frame, followed by the next packet at 0020h
+            spi.respond(&[0x20, 0x00, 0x08, 0x00, 0x80, 0x00]);
+            spi.respond(&[1, 2, 3, 4]);
+        }
+
+        let mut peeked = [0u8; 4];
+        enc.peek(&mut peeked).unwrap();
+        let written = spi.take_written();
+        let rbm = (Op::RBM as u8) | 0x1a;
+        assert!(written.ends_with(&[wcr(ERDPTL, 0), wcr(ERDPTH, 0), vec![rbm], vec![rbm]]));
+        assert_eq!(enc.next_packet, 0);
+
+        let mut received = [0u8; 4];
+        enc.receive(&mut received).unwrap();
+        assert_eq!(received, peeked);
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(ERDPTL, 0)));
+        assert!(written.contains(&wcr(ERXRDPTL, 0x1f)));
+        assert_eq!(enc.next_packet, 0x20);
+        assert_eq!(enc.stats().rx_frames, 1);
+    }
+
+    #[test]
+    fn receive_rejects_frames_shorter_than_crc() {
+        for (byte_count, expected) in [
+            (0, Err(Error::RuntFrame)),
+            (3, Err(Error::RuntFrame)),
+            (4, Ok(0)),
+            (60, Ok(56)),
+        ] {
+            let (mut enc, spi) = ready_driver();
+            // EPKTCNT, EIR
+            spi.respond(&[0, 1, 0, 0, 0, 0]);
+            spi.respond(&[0x40, 0x00, byte_count, 0x00, 0x80, 0x00]);
+            let result = enc.receive(&mut [0u8; 64]);
+            assert_eq!(
+                result.map(|packet| packet.unwrap().total),
+                expected,
+                "{byte_count}"
+            );
+
+            // The packet is dropped either way.
+            let written = spi.take_written();
+            assert!(written.ends_with(&[
+                wcr(ERXRDPTL, 0x3f),
+                wcr(ERXRDPTH, 0x00),
+                vec![ECON2.opcode(Op::BFS), Econ2::PKTDEC.bits()],
+            ]));
+        }
+    }
+
+    #[test]
+    fn receive_all_skips_runt_frames() {
+        let (mut enc, spi) = ready_driver();
+        // EPKTCNT, EIR
+        spi.respond(&[0, 2, 0, 0, 0, 0]);
+        // Receive status vector of a runt, followed by the next packet at 0010h
+        spi.respond(&[0x10, 0x00, 0x03, 0x00, 0x80, 0x00]);
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        // Receive status vector of a 4-byte frame, followed by the next packet at 0020h
+        spi.respond(&[0x20, 0x00, 0x08, 0x00, 0x80, 0x00]);
+        spi.respond(&[1, 2, 3, 4]);
+
+        let mut received = Vec::new();
+        let count = enc
+            .receive_all(|packet, len| received.push((packet.to_vec(), len)))
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(received, [(vec![1, 2, 3, 4], 4)]);
+        assert_eq!(enc.next_packet, 0x20);
+    }
+
+    #[test]
+    fn memory_access_programs_pointer_first() {
+        let (mut enc, spi) = driver();
+        spi.respond(&[1, 2, 3]);
+        let mut buf = [0u8; 3];
+        enc.read_memory(0x1234, &mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3]);
+        enc.write_memory(0x0ffe, &[4, 5]).unwrap();
+
+        let rbm = (Op::RBM as u8) | 0x1a;
+        let wbm = (Op::WBM as u8) | 0x1a;
+        assert_eq!(
+            spi.take_written(),
+            [
+                wcr(ERDPTL, 0x34),
+                wcr(ERDPTH, 0x12),
+                vec![rbm],
+                wcr(EWRPTL, 0xfe),
+                wcr(EWRPTH, 0x0f),
+                vec![wbm, 4, 5],
+            ]
+        );
+    }
+
+    #[test]
+    fn transmit_reports_late_collision() {
+        let (mut enc, spi) = ready_driver();
+        enc.set_transmit_retries(0);
+        // ECON1, ETXST
+        spi.respond(&[0; 9]);
+        // ECON1, ESTAT with LATECOL set, ETXND
+        spi.respond(&[0, 0, 0, 0, 0b1_0000, 0]);
+        spi.respond(&[0; 6]);
+        // Transmit status vector with a late collision
+        spi.respond(&[0, 0, 1, 0x20, 0, 0, 0]);
+        let dst = [0xff; 6];
+        let result = enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46]);
+        assert_eq!(result, Err(Error::LateCollision));
+
+        let written = spi.take_written();
+        // TXIF and TXERIF are cleared before the transmission, and after it was aborted.
+        let clear_eir = |mask| vec![EIR.opcode(Op::BFC), mask];
+        assert!(written.contains(&clear_eir(0b1010)));
+        assert!(written.ends_with(&[vec![ESTAT.opcode(Op::BFC), 0b1_0010], clear_eir(0b10)]));
+    }
+
+    #[test]
+    fn stats_count_frames_and_errors() {
+        let (mut enc, spi) = ready_driver();
+        enc.set_transmit_retries(1);
+        // ECON1, ETXST
+        spi.respond(&[0; 9]);
+        // ECON1, ESTAT with TXABRT set, ETXND, transmit status vector
+        spi.respond(&[0, 0, 0, 0, 0b10, 0]);
+        spi.respond(&[0; 13]);
+        // ECON1, ESTAT, ETXND
+        spi.respond(&[0; 12]);
+        // Transmit status vector with 3 collisions
+        spi.respond(&[0, 0, 3, 0, 0, 0, 0]);
+        let dst = [0xff; 6];
+        enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46])
+            .unwrap();
+        // Polling the finished transmission again does not count it twice.
+        enc.poll_transmit().unwrap();
+
+        // EPKTCNT, EIR with RXERIF set
+        spi.respond(&[0, 1, 0, 0, 0b1, 0]);
+        // Receive status vector with a CRC error
+        spi.respond(&[0x40, 0x00, 0x04, 0x00, 0x10, 0x00]);
+        enc.receive(&mut [0u8; 8]).unwrap();
+
+        let expected = Stats {
+            rx_frames: 1,
+            rx_crc_errors: 1,
+            rx_overflows: 1,
+            tx_frames: 1,
+            tx_aborts: 1,
+            tx_collisions: 3,
+        };
+        assert_eq!(*enc.stats(), expected);
+
+        enc.reset_stats();
+        assert_eq!(*enc.stats(), Stats::default());
+    }
+
+    #[test]
+    fn packets_dropped_counts_overflows() {
+        let (mut enc, spi) = ready_driver();
+        // EIR with RXERIF set, then clear
+        spi.respond(&[0, 0b1, 0, 0, 0, 0]);
+        assert_eq!(enc.packets_dropped(), Ok(1));
+        assert_eq!(enc.packets_dropped(), Ok(1));
+        assert!(spi.take_written().contains(&vec![EIR.opcode(Op::BFC), 0b1]));
+
+        // An overflow noticed by `rx_overflow` is counted as well.
+        spi.respond(&[0, 0b1, 0]);
+        assert_eq!(enc.rx_overflow(), Ok(true));
+        assert_eq!(enc.packets_dropped(), Ok(2));
+    }
+
+    #[test]
+    fn set_crc_check_keeps_other_filters() {
+        let (mut enc, spi) = ready_driver();
+        enc.set_receive_filter(ReceiveFilter::UNICAST).unwrap();
+        enc.set_crc_check(true).unwrap();
+        let filter = ReceiveFilter::UNICAST | ReceiveFilter::CRC_CHECK;
+        assert_eq!(enc.receive_filter(), filter);
+        enc.set_crc_check(false).unwrap();
+        assert_eq!(enc.receive_filter(), ReceiveFilter::UNICAST);
+        let mut expected = bank_switch(Bank::Bank2, Bank::Bank1);
+        expected.extend([
+            wcr(ERXFCON, 0b1000_0000),
+            wcr(ERXFCON, 0b1010_0000),
+            wcr(ERXFCON, 0b1000_0000),
+        ]);
+        assert_eq!(spi.take_written(), expected);
+    }
+
+    #[test]
+    fn recover_reprograms_mac_and_filters() {
+        let (enc, spi) = driver();
+        let config = Enc28j60Config {
+            mac_address: [0x02, 0, 0, 0, 0, 1],
+            duplex: Duplex::Half,
+            ..Enc28j60Config::default()
+        };
+        spi.respond(&EREVID_B7);
+        let mut enc = enc.initialize_with(&mut NoDelay, &config).unwrap();
+        let filter = ReceiveFilter::UNICAST | ReceiveFilter::BROADCAST;
+        enc.set_receive_filter(filter).unwrap();
+        enc.add_multicast_group(&[0x01, 0, 0x5e, 0, 0, 1]).unwrap();
+        spi.take_written();
+
+        // ESTAT with CLKRDY set
+        spi.respond(&[0, 0b1, 0]);
+        spi.respond(&EREVID_B7);
+        enc.recover(&mut NoDelay).unwrap();
+
+        let written = spi.take_written();
+        assert_eq!(written[0], rcr(ESTAT));
+        assert!(written.contains(&vec![0xff]));
+        assert!(written.contains(&wcr(MAADR6, 0x01)));
+        // Half duplex leaves MACON3.FULDPX clear.
+        assert!(written.contains(&wcr(MACON3, 0b0011_0010)));
+        // The hash table is programmed again before the hash table filter is enabled.
+        let index = multicast_hash_index(&[0x01, 0, 0x5e, 0, 0, 1]);
+        let eht = written
+            .iter()
+            .position(|w| *w == wcr(EHT[usize::from(index >> 3)], 1 << (index & 0b111)))
+            .unwrap();
+        let full_filter = filter | ReceiveFilter::HASH_TABLE;
+        assert!(written[eht..].ends_with(&[wcr(ERXFCON, full_filter.bits())]));
+        assert!(!written.contains(&wcr(ERXFCON, filter.bits())));
+        assert_eq!(enc.receive_filter(), full_filter);
+    }
+
+    #[test]
+    fn recover_restores_pattern_match_only_filter() {
+        let (mut enc, spi) = ready_driver();
+        enc.set_receive_filter(ReceiveFilter::empty()).unwrap();
+        enc.set_pattern_match(0x0c, 0b11, 0x1234).unwrap();
+        assert_eq!(enc.receive_filter(), ReceiveFilter::PATTERN_MATCH);
+        spi.take_written();
+
+        // ESTAT with CLKRDY set
+        spi.respond(&[0, 0b1, 0]);
+        spi.respond(&EREVID_B7);
+        enc.recover(&mut NoDelay).unwrap();
+
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(EPMOL, 0x0c)));
+        assert!(written.contains(&wcr(EPMM0, 0b11)));
+        assert!(written.contains(&wcr(EPMCSL, 0x34)));
+        assert_eq!(enc.receive_filter(), ReceiveFilter::PATTERN_MATCH);
+        // The filter never becomes empty, which would accept every frame.
+        assert!(!written.contains(&wcr(ERXFCON, 0)));
+        assert!(written.ends_with(&[wcr(ERXFCON, ReceiveFilter::PATTERN_MATCH.bits())]));
+    }
+
+    #[test]
+    fn accept_programs_only_needed_filters() {
+        let (mut enc, spi) = ready_driver();
+        let group = [0x03, 0, 0, 0, 0, 0x42];
+        let index = multicast_hash_index(&group);
+        enc.accept(&[DEFAULT_MAC_ADDRESS, [0xff; 6], group])
+            .unwrap();
+
+        let written = spi.take_written();
+        let eht = EHT[usize::from(index >> 3)];
+        assert!(written.contains(&wcr(eht, 1 << (index & 0b111))));
+        let filter = ReceiveFilter::UNICAST | ReceiveFilter::BROADCAST | ReceiveFilter::HASH_TABLE;
+        assert_eq!(written.last(), Some(&wcr(ERXFCON, filter.bits())));
+
+        // Without an address to hash, the table is cleared and its filter left disabled.
+        enc.accept(&[DEFAULT_MAC_ADDRESS]).unwrap();
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(eht, 0)));
+        assert_eq!(
+            written.last(),
+            Some(&wcr(ERXFCON, ReceiveFilter::UNICAST.bits()))
+        );
+    }
+
+    #[test]
+    fn transmit_rejects_oversize_payload() {
+        let (mut enc, spi) = ready_driver();
+        let dst = [0xff; 6];
+        let result = enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 2048]);
+        assert_eq!(result, Err(Error::FrameTooLong));
+        // Nothing was written to the buffer memory, nor was the transmit logic touched.
+        assert!(spi.take_written().is_empty());
+    }
+
+    #[test]
+    fn transmit_writes_per_packet_control_byte() {
+        let dst = [0xff; 6];
+        for (options, control) in [
+            (TransmitOptions::default(), 0b0000),
+            (
+                TransmitOptions {
+                    override_defaults: true,
+                    append_crc: false,
+                    pad: false,
+                    allow_huge: false,
+                },
+                0b0001,
+            ),
+            (
+                TransmitOptions {
+                    override_defaults: true,
+                    append_crc: true,
+                    pad: true,
+                    allow_huge: false,
+                },
+                0b0111,
+            ),
+        ] {
+            let (mut enc, spi) = ready_driver();
+            enc.transmit_with(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46], options)
+                .unwrap();
+            let bytes = buffer_writes(&spi.take_written());
+            assert_eq!(bytes[0], control, "{options:?}");
+            assert_eq!(bytes[1..7], dst);
+        }
+    }
+
+    #[test]
+    fn transmit_retries_after_abort() {
+        let (mut enc, spi) = ready_driver();
+        enc.set_transmit_retries(1);
+        // ECON1, ETXST
+        spi.respond(&[0; 9]);
+        // ECON1, ESTAT with TXABRT set, ETXND, transmit status vector
+        spi.respond(&[0, 0, 0, 0, 0b10, 0]);
+        spi.respond(&[0; 13]);
+        // ECON1, ESTAT, ETXND, then a transmit status vector reporting success
+        spi.respond(&[0; 12]);
+        spi.respond(&[0x40, 0, 0x80, 0, 0, 0, 0]);
+        let dst = [0xff; 6];
+        let status = enc
+            .transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46])
+            .unwrap();
+        assert!(status.done());
+
+        // The transmit logic is reset after the abort, and TXRTS set once more.
+        let written = spi.take_written();
+        let txrts = vec![ECON1.opcode(Op::BFS), Econ1::TXRTS.bits()];
+        let txrst = vec![ECON1.opcode(Op::BFS), Econ1::TXRST.bits()];
+        let arms: Vec<_> = (0..written.len())
+            .filter(|&i| written[i] == txrts)
+            .collect();
+        assert_eq!(arms.len(), 2);
+        assert!(written[arms[0]..arms[1]].contains(&txrst));
+        assert_eq!(enc.stats().tx_aborts, 1);
+        assert_eq!(enc.stats().tx_frames, 1);
+
+        // Without retries, the abort is reported.
+        enc.set_transmit_retries(0);
+        spi.respond(&[0; 9]);
+        spi.respond(&[0, 0, 0, 0, 0b10, 0]);
+        let result = enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46]);
+        assert_eq!(result, Err(Error::Aborted));
+    }
+
+    #[test]
+    fn poll_transmit_reports_state_transitions() {
+        let (mut enc, spi) = ready_driver();
+        let dst = [0xff; 6];
+        let options = TransmitOptions::default();
+        enc.start_transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46], options)
+            .unwrap();
+        spi.take_written();
+
+        // ECON1 with TXRTS still set
+        spi.respond(&[0, Econ1::TXRTS.bits(), 0]);
+        assert_eq!(enc.poll_transmit(), Ok(TxState::InProgress));
+        // Only ECON1 is read while the transmission is in progress.
+        assert_eq!(spi.take_written(), [rcr(ECON1)]);
+
+        // ECON1, ESTAT, ETXND, then the transmit status vector
+        spi.respond(&[0; 12]);
+        let tsv = [0x40, 0, 0x80, 0, 0, 0, 0];
+        spi.respond(&tsv);
+        assert_eq!(
+            enc.poll_transmit(),
+            Ok(TxState::Done(TxStatus::from_bytes(tsv)))
+        );
+
+        enc.start_transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46], options)
+            .unwrap();
+        // ECON1, ESTAT with TXABRT set, ETXND, transmit status vector
+        spi.respond(&[0, 0, 0, 0, 0b10, 0]);
+        spi.respond(&[0; 13]);
+        assert_eq!(
+            enc.poll_transmit(),
+            Ok(TxState::Aborted(TxStatus::default()))
+        );
+    }
+
+    #[test]
+    fn start_transmit_rejects_frame_while_busy() {
+        let (mut enc, spi) = ready_driver();
+        let dst = [0xff; 6];
+        let options = TransmitOptions::default();
+        enc.start_transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46], options)
+            .unwrap();
+        spi.take_written();
+
+        // The previous transmission has not been polled yet.
+        let result = enc.start_transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[1; 46], options);
+        assert_eq!(result, Err(Error::Busy));
+        assert!(spi.take_written().is_empty());
+
+        // ECON1, ESTAT, ETXND, then the transmit status vector
+        spi.respond(&[0; 12]);
+        spi.respond(&[0x40, 0, 0x80, 0, 0, 0, 0]);
+        assert!(matches!(enc.poll_transmit(), Ok(TxState::Done(_))));
+        spi.take_written();
+
+        // ECON1 with TXRTS set by someone else
+        spi.respond(&[0, Econ1::TXRTS.bits(), 0]);
+        let result = enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[1; 46]);
+        assert_eq!(result, Err(Error::Busy));
+        assert_eq!(spi.take_written(), [rcr(ECON1)]);
+
+        enc.start_transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[1; 46], options)
+            .unwrap();
+    }
+
+    #[test]
+    fn transmit_vectored_concatenates_segments() {
+        let (mut enc, spi) = ready_driver();
+        let dst = [0xff; 6];
+        let src = [0x02, 0, 0, 0, 0, 1];
+        enc.transmit_vectored(&dst, &src, 0x0800, &[&[1, 2], &[], &[3, 4, 5]])
+            .unwrap();
+
+        let written = spi.take_written();
+        let expected = [
+            0x00, // Per-packet control byte
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // Destination
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x01, // Source
+            0x08, 0x00, // EtherType
+            1, 2, 3, 4, 5, // Segments
+        ];
+        assert_eq!(buffer_writes(&written), expected);
+        // ETXND points to the last byte, counting from ETXST at 0.
+        assert!(written.contains(&wcr(ETXNDL, 19)));
+        assert!(written.contains(&wcr(ETXNDH, 0)));
+    }
+
+    #[test]
+    fn transmit_vlan_inserts_tag() {
+        let (mut enc, spi) = ready_driver();
+        let dst = [0xff; 6];
+        let src = [0x02, 0, 0, 0, 0, 1];
+        enc.transmit_vlan(&dst, &src, 0x123, 5, 0x0800, &[0xde, 0xad])
+            .unwrap();
+
+        let bytes = buffer_writes(&spi.take_written());
+        // The per-packet control byte overrides MACON3 to allow the longer frame.
+        assert_eq!(bytes[0], 0b1111);
+        assert_eq!(bytes[1..7], dst);
+        assert_eq!(bytes[7..13], src);
+        assert_eq!(
+            bytes[13..],
+            [0x81, 0x00, 0xa1, 0x23, 0x08, 0x00, 0xde, 0xad]
+        );
+    }
+
+    #[test]
+    fn transmit_vlan_allows_tag_beyond_max_frame_length() {
+        let (mut enc, _spi) = ready_driver();
+        let dst = [0xff; 6];
+        let src = [0x02, 0, 0, 0, 0, 1];
+        assert!(
+            enc.transmit_vlan(&dst, &src, 1, 0, 0x0800, &[0; 1500])
+                .is_ok()
+        );
+        let result = enc.transmit_vlan(&dst, &src, 1, 0, 0x0800, &[0; 1501]);
+        assert_eq!(result, Err(Error::FrameTooLong));
+    }
+
+    #[test]
+    fn send_pause_builds_pause_frame() {
+        let (mut enc, spi) = ready_driver();
+        enc.set_mac_address(&[0x02, 0x11, 0x22, 0x33, 0x44, 0x55])
+            .unwrap();
+        spi.take_written();
+        enc.send_pause(0xabcd).unwrap();
+
+        let bytes = buffer_writes(&spi.take_written());
+        let expected = [
+            0x00, // Per-packet control byte
+            0x01, 0x80, 0xc2, 0x00, 0x00, 0x01, // Destination
+            0x02, 0x11, 0x22, 0x33, 0x44, 0x55, // Source
+            0x88, 0x08, // MAC control
+            0x00, 0x01, // PAUSE opcode
+            0xab, 0xcd, // Quanta
+        ];
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn flow_control_depends_on_duplex() {
+        let (mut enc, spi) = ready_driver();
+        enc.start_flow_control(0x1234).unwrap();
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(EPAUSL, 0x34)));
+        assert!(written.contains(&wcr(MACON1, Macon1::TXPAUS.bits())));
+        assert!(written.ends_with(&[wcr(EFLOCON, 0b10)]));
+        enc.stop_flow_control().unwrap();
+        assert!(spi.take_written().ends_with(&[wcr(EFLOCON, 0b11)]));
+
+        // Half-duplex mode only has FCEN0, which turns backpressure on and off.
+        let (enc, spi) = driver();
+        spi.respond(&EREVID_B7);
+        let mut enc = enc
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Half)
+            .unwrap();
+        spi.take_written();
+        enc.start_flow_control(0x1234).unwrap();
+        let written = spi.take_written();
+        assert!(!written.contains(&wcr(EPAUSL, 0x34)));
+        assert!(written.ends_with(&[wcr(EFLOCON, 0b01)]));
+        enc.stop_flow_control().unwrap();
+        assert!(spi.take_written().ends_with(&[wcr(EFLOCON, 0b00)]));
+    }
+
+    #[test]
+    fn receive_reports_and_clears_overflow() {
+        let (mut enc, spi) = ready_driver();
+        // EPKTCNT, EIR with RXERIF set, receive status vector of a frame holding only the CRC
+        let rsv = [0x00, 0x00, 0x04, 0x00, 0x80, 0x00];
+        spi.respond(&[0, 1, 0, 0, 0b0000_0001, 0]);
+        spi.respond(&rsv);
+
+        let mut buf = [0u8; 8];
+        let packet = enc.receive(&mut buf).unwrap().unwrap();
+        assert!(packet.overflowed);
+        let written = spi.take_written();
+        assert!(written.contains(&vec![EIR.opcode(Op::BFC), 0b0000_0001]));
+
+        spi.respond(&[0, 1, 0, 0, 0b0000_0000, 0]);
+        spi.respond(&rsv);
+        let packet = enc.receive(&mut buf).unwrap().unwrap();
+        assert!(!packet.overflowed);
+    }
+
+    #[test]
+    fn clear_interrupts_skips_read_only_flags() {
+        let all = InterruptFlags::PACKET
+            | InterruptFlags::DMA
+            | InterruptFlags::LINK
+            | InterruptFlags::TRANSMIT
+            | InterruptFlags::TX_ERROR
+            | InterruptFlags::RX_ERROR;
+        assert_eq!(all.clear_mask(), 0b0010_1011);
+        assert_eq!(InterruptFlags::PACKET.clear_mask(), 0);
+        assert_eq!(InterruptFlags::LINK.clear_mask(), 0);
+
+        let (mut enc, spi) = ready_driver();
+        enc.clear_interrupts(InterruptFlags::TRANSMIT | InterruptFlags::LINK)
+            .unwrap();
+        assert_eq!(spi.take_written(), [vec![EIR.opcode(Op::BFC), 0b1000]]);
+    }
+
+    #[test]
+    fn interrupt_pending_follows_int_pin() {
+        let mut int = MockPin::default();
+        let mut enc = Enc28j60::new(MockSpi::default(), int.clone(), MockPin::default());
+
+        // INT is active low.
+        int.set_high().unwrap();
+        assert_eq!(enc.interrupt_pending(), Ok(false));
+        int.set_low().unwrap();
+        assert_eq!(enc.interrupt_pending(), Ok(true));
+    }
+
+    #[test]
+    fn read_estat_decodes_flags() {
+        let (mut enc, spi) = driver();
+        spi.respond(&[0, 0b0101_0011, 0]);
+        let estat = enc.read_estat().unwrap();
+        assert!(estat.clk_ready() && estat.tx_abort() && estat.latecol() && estat.bufer());
+        assert!(!estat.rx_busy());
+    }
+
+    #[test]
+    fn initialize_checks_revision() {
+        let cases = [
+            (0x00, Err(Error::DeviceNotResponding)),
+            (0xff, Err(Error::DeviceNotResponding)),
+            (0x02, Ok(())),
+            (0x04, Ok(())),
+            (0x05, Ok(())),
+            (0x06, Ok(())),
+            (0x08, Err(Error::UnsupportedRevision(0x08))),
+        ];
+        for (erevid, expected) in cases {
+            let (enc, spi) = driver();
+            spi.respond(&[0, erevid, 0]);
+            let result = enc.initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full);
+            let result = result.map(|_| ()).map_err(|e| e.error);
+            assert_eq!(result, expected, "EREVID {erevid:#04x}");
+        }
+    }
+
+    #[test]
+    fn initialize_can_be_retried_after_failure() {
+        let (enc, spi) = driver();
+        // EREVID reads back as 00h, as if the device were not powered yet.
+        let error = enc
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(error.error, Error::DeviceNotResponding);
+
+        spi.take_written();
+        spi.respond(&EREVID_B7);
+        let mut enc = error
+            .driver
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        assert_eq!(enc.receive(&mut [0u8; 8]), Ok(None));
+    }
+
+    #[test]
+    fn econ2_flags_compose() {
+        assert_eq!((Econ2::AUTOINC | Econ2::PKTDEC).bits(), 0b1100_0000);
+        assert_eq!((Econ2::PWRSV | Econ2::VRPS).bits(), 0b0010_1000);
+
+        let (mut enc, spi) = ready_driver();
+        enc.run([sequence::decrement_packet_count()]).unwrap();
+        assert_eq!(
+            spi.take_written(),
+            [vec![ECON2.opcode(Op::BFS), 0b0100_0000]]
+        );
+    }
+
+    #[test]
+    fn phy_flags_compose() {
+        assert_eq!(PhCon1::PDPXMD.bits(), 0x0100);
+        assert_eq!((PhCon1::PDPXMD | PhCon1::PLOOPBK).bits(), 0x4100);
+        assert_eq!(PhCon2::HDLDIS.bits(), 0x0100);
+        assert_eq!(PhCon2::FRCLNK.bits(), 0x4000);
+        assert!(PhStat1::from(0x1804).contains(PhStat1::LLSTAT));
+        assert_eq!(
+            LinkStatus::from_phstat2(0x0600),
+            LinkStatus {
+                up: true,
+                full_duplex: true
+            }
+        );
+
+        let (mut enc, spi) = ready_driver();
+        enc.set_led_mode(LedMode::Link, LedMode::Receive).unwrap();
+        let written = spi.take_written();
+        assert!(written.ends_with(&[wcr(MIWRL, 0x22), wcr(MIWRH, 0x34)]));
+    }
+
+    #[test]
+    fn initialize_enables_packet_interrupt() {
+        let (enc, spi) = driver();
+        spi.respond(&EREVID_B7);
+        enc.initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        assert!(spi.take_written().contains(&wcr(EIE, 0b1100_0000)));
+    }
+
+    #[test]
+    fn read_reg_interprets_value() {
+        let (mut enc, spi) = driver();
+        spi.respond(&[0, 0b0000_1100, 0]);
+        let econ1: Econ1 = enc.read_reg(ECON1).unwrap();
+        assert_eq!(econ1, Econ1::TXRTS | Econ1::RXEN);
+    }
+
+    #[test]
+    fn set_macon4_writes_defer() {
+        let (mut enc, spi) = driver();
+        enc.set_macon4(Macon4::DEFER).unwrap();
+        let mut expected = bank_switch(Bank::Bank0, Bank::Bank2);
+        expected.push(wcr(MACON4, 0b0100_0000));
+        assert_eq!(spi.take_written(), expected);
+
+        spi.respond(&EREVID_B7);
+        enc.initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Half)
+            .unwrap();
+        assert!(spi.take_written().contains(&wcr(MACON4, 0b0100_0000)));
+    }
+
+    #[test]
+    fn write_u16_writes_low_byte_first() {
+        let (mut enc, spi) = driver();
+        enc.write_u16(ERDPTL, ERDPTH, 0x1234).unwrap();
+        assert_eq!(spi.take_written(), [wcr(ERDPTL, 0x34), wcr(ERDPTH, 0x12)]);
+    }
+
+    #[test]
+    fn read_u16_combines_low_and_high_bytes() {
+        let (mut enc, spi) = driver();
+        spi.respond(&[0, 0x34, 0, 0, 0x12, 0]);
+        assert_eq!(enc.read_u16(ERDPTL, ERDPTH), Ok(0x1234));
+    }
+
+    #[test]
+    fn set_mac_address_writes_octets_in_wire_order() {
+        let (mut enc, spi) = driver();
+        let mac = [0x02, 0x11, 0x22, 0x33, 0x44, 0x55];
+        enc.set_mac_address(&mac).unwrap();
+
+        let written: Vec<_> = spi
+            .take_written()
+            .into_iter()
+            .filter(|bytes| bytes[0] & 0b1110_0000 == Op::WCR as u8)
+            .collect();
+        let expected: Vec<_> = MAADR
+            .into_iter()
+            .zip(mac)
+            .map(|(reg, octet)| wcr(reg, octet))
+            .collect();
+        assert_eq!(written, expected);
+        assert_eq!(written[0], wcr(MAADR1, 0x02));
+    }
+
+    #[test]
+    fn receive_frees_buffer_up_to_next_packet() {
+        let (mut enc, spi) = ready_driver();
+        // EPKTCNT, EIR
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        // Receive status vector: next packet at 0x0040, 4 bytes and the CRC, received OK
+        spi.respond(&[0x40, 0x00, 0x08, 0x00, 0x80, 0x00]);
+        spi.respond(&[1, 2, 3, 4]);
+
+        let mut buf = [0u8; 8];
+        let packet = enc.receive(&mut buf).unwrap().unwrap();
+        assert_eq!((packet.copied, packet.total), (4, 4));
+        assert!(packet.status.received_ok());
+        assert_eq!(buf[..4], [1, 2, 3, 4]);
+
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(ERXRDPTL, 0x3f)));
+        assert!(written.contains(&wcr(ERXRDPTH, 0x00)));
+        // The receive buffer bounds are not read back from the device.
+        assert!(!written.contains(&rcr(ERXSTL)) && !written.contains(&rcr(ERXNDL)));
+
+        // The next packet is read from the saved next packet pointer.
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        spi.respond(&[0x00, 0x00, 0x04, 0x00, 0x80, 0x00]);
+        enc.receive(&mut buf).unwrap();
+        let written = spi.take_written();
+        assert!(written.contains(&wcr(ERDPTL, 0x40)));
+        assert!(written.contains(&wcr(ERDPTH, 0x00)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn receive_vec_allocates_frame_length() {
+        let (mut enc, spi) = ready_driver();
+        assert_eq!(enc.receive_vec(), Ok(None));
+
+        // EPKTCNT, EIR
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        // Receive status vector: next packet at 0x0040, 3 bytes and the CRC, received OK
+        spi.respond(&[0x40, 0x00, 0x07, 0x00, 0x80, 0x00]);
+        spi.respond(&[1, 2, 3]);
+        assert_eq!(enc.receive_vec(), Ok(Some(vec![1, 2, 3])));
+        assert!(spi.take_written().contains(&wcr(ERXRDPTL, 0x3f)));
+    }
+
+    #[test]
+    fn consecutive_mem_reads_rely_on_autoinc() {
+        let (enc, spi) = driver();
+        spi.respond(&EREVID_B7);
+        let mut enc = enc
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        assert!(
+            spi.take_written()
+                .contains(&vec![ECON2.opcode(Op::BFS), 0x80])
+        );
+
+        // The second read continues from where the first left off, without moving ERDPT.
+        spi.respond(&[1, 2, 3, 4]);
+        let mut first = [0u8; 2];
+        let mut second = [0u8; 2];
+        enc.mem_read(&mut first).unwrap();
+        enc.mem_read(&mut second).unwrap();
+        let rbm = (Op::RBM as u8) | 0x1a;
+        assert_eq!(spi.take_written(), [vec![rbm], vec![rbm]]);
+        assert_eq!((first, second), ([1, 2], [3, 4]));
+
+        enc.set_autoinc(false).unwrap();
+        assert_eq!(spi.take_written(), [vec![ECON2.opcode(Op::BFC), 0x80]]);
+    }
+
+    #[test]
+    fn checksum_waits_for_dma() {
+        let (mut enc, spi) = driver();
+        // ECON1 with DMAST set, then cleared
+        spi.respond(&[0, 0b0011_0000, 0, 0, 0b0001_0000, 0]);
+        // EDMACSL, EDMACSH
+        spi.respond(&[0, 0x34, 0, 0, 0x12, 0]);
+        assert_eq!(enc.checksum(0x1000, 0x1013), Ok(0x1234));
+
+        let expected = [
+            wcr(EDMASTL, 0x00),
+            wcr(EDMASTH, 0x10),
+            wcr(EDMANDL, 0x13),
+            wcr(EDMANDH, 0x10),
+            vec![ECON1.opcode(Op::BFS), 0b0011_0000],
+            rcr(ECON1),
+            rcr(ECON1),
+            vec![EIR.opcode(Op::BFC), 0b0010_0000],
+            vec![ECON1.opcode(Op::BFC), 0b0001_0000],
+            rcr(EDMACSL),
+            rcr(EDMACSH),
+        ];
+        assert_eq!(spi.take_written(), expected);
+    }
+
+    #[test]
+    fn dma_copy_programs_ranges() {
+        let (mut enc, spi) = driver();
+        enc.dma_copy(0x0123, 0x0456, 0x1000).unwrap();
+
+        let expected = [
+            wcr(EDMADSTL, 0x00),
+            wcr(EDMADSTH, 0x10),
+            wcr(EDMASTL, 0x23),
+            wcr(EDMASTH, 0x01),
+            wcr(EDMANDL, 0x56),
+            wcr(EDMANDH, 0x04),
+            // DMAST without CSUMEN
+            vec![ECON1.opcode(Op::BFS), 0b0010_0000],
+            rcr(ECON1),
+            vec![EIR.opcode(Op::BFC), 0b0010_0000],
+        ];
+        assert_eq!(spi.take_written(), expected);
+    }
+
+    #[test]
+    fn enable_wake_on_lan_accepts_magic_packets() {
+        let (mut enc, spi) = ready_driver();
+        enc.enable_wake_on_lan().unwrap();
+
+        let written = spi.take_written();
+        assert!(written.ends_with(&[
+            wcr(ERXFCON, 0b0010_1000),
+            vec![EIE.opcode(Op::BFS), 0b1100_0000],
+        ]));
+    }
+
+    #[test]
+    fn first_receive_reads_from_rx_start() {
+        let (mut enc, spi) = driver();
+        // Leave ERDPT somewhere else, as a previous user of the device might have.
+        enc.write_u16(ERDPTL, ERDPTH, 0x1234).unwrap();
+        spi.respond(&EREVID_B7);
+        let mut enc = enc
+            .initialize(&mut NoDelay, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+            .unwrap();
+        spi.take_written();
+
+        // EPKTCNT, EIR, receive status vector of a frame holding only the CRC
+        spi.respond(&[0, 1, 0, 0, 0, 0]);
+        spi.respond(&[0x00, 0x00, 0x04, 0x00, 0x80, 0x00]);
+        enc.receive(&mut [0u8; 8]).unwrap();
+
+        // ERDPT is programmed with RX_START before the receive status vector is read.
+        let rbm = (Op::RBM as u8) | 0x1a;
+        let written = spi.take_written();
+        let first_read = written.iter().position(|bytes| bytes[0] == rbm).unwrap();
+        assert!(written[..first_read].ends_with(&[wcr(ERDPTL, 0x00), wcr(ERDPTH, 0x00)]));
+    }
+
+    #[test]
+    fn receive_streaming_matches_receive() {
+        let frame: Vec<u8> = (0..100).collect();
+        let script = |spi: &MockSpi| {
+            // EPKTCNT, EIR
+            spi.respond(&[0, 1, 0, 0, 0, 0]);
+            // Receive status vector: next packet at 0x0080, 100 bytes and the CRC, received OK
+            spi.respond(&[0x80, 0x00, 104, 0x00, 0x80, 0x00]);
+            spi.respond(&frame);
+        };
+
+        let (mut enc, spi) = ready_driver();
+        script(&spi);
+        let mut buf = [0u8; 128];
+        let packet = enc.receive(&mut buf).unwrap().unwrap();
+        let whole_written = spi.take_written();
+
+        let (mut enc, spi) = ready_driver();
+        script(&spi);
+        let mut streamed = Vec::new();
+        let mut chunks = 0;
+        let streamed_packet = enc
+            .receive_streaming(|chunk| {
+                streamed.extend_from_slice(chunk);
+                chunks += 1;
+            })
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(streamed_packet, packet);
+        assert_eq!(streamed, buf[..packet.copied]);
+        assert_eq!(chunks, 2);
+        // Apart from the buffer reads, the same commands are issued.
+        let rbm = (Op::RBM as u8) | 0x1a;
+        let without_reads = |written: Vec<Vec<u8>>| -> Vec<Vec<u8>> {
+            written
+                .into_iter()
+                .filter(|bytes| bytes[0] != rbm)
+                .collect()
+        };
+        assert_eq!(
+            without_reads(spi.take_written()),
+            without_reads(whole_written)
+        );
+
+        // The mock reads back EPKTCNT as zero.
+        let packet = enc.receive_streaming(|_| panic!("no packet is pending"));
+        assert_eq!(packet, Ok(None));
+    }
+
+    #[test]
+    fn frames_drains_pending_packets() {
+        let (mut enc, spi) = ready_driver();
+        for next in [0x40u8, 0x80] {
+            // EPKTCNT, EIR
+            spi.respond(&[0, 1, 0, 0, 0, 0]);
+            // Receive status vector: 2 bytes and the CRC, received OK
+            spi.respond(&[next, 0x00, 0x06, 0x00, 0x80, 0x00]);
+            spi.respond(&[next, next]);
+        }
+
+        let frames: Vec<_> = enc.frames().map(Result::unwrap).collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data(), [0x40, 0x40]);
+        assert_eq!(frames[1].data(), [0x80, 0x80]);
+        assert!(frames[1].status().received_ok());
+    }
+
+    #[test]
+    fn receive_and_transmit_fail_after_reset() {
+        let (mut enc, spi) = ready_driver();
+        enc.reset_via_spi(&mut NoDelay).unwrap();
+        spi.take_written();
+
+        let mut buf = [0u8; 8];
+        assert_eq!(enc.receive(&mut buf), Err(Error::NotInitialized));
+        let dst = [0xff; 6];
+        let result = enc.transmit(&dst, &DEFAULT_MAC_ADDRESS, 0x0800, &[0; 46]);
+        assert_eq!(result, Err(Error::NotInitialized));
+        assert!(spi.take_written().is_empty());
+    }
+
+    #[test]
+    fn bank_switch_commands_change_only_differing_bits() {
+        let bfc = ECON1.opcode(Op::BFC);
+        let bfs = ECON1.opcode(Op::BFS);
+        assert_eq!(
+            bank_switch_commands(Some(Bank::Bank0), Bank::Bank1),
+            [None, Some([bfs, 0b01])]
+        );
+        assert_eq!(
+            bank_switch_commands(Some(Bank::Bank1), Bank::Bank2),
+            [Some([bfc, 0b01]), Some([bfs, 0b10])]
+        );
+        assert_eq!(
+            bank_switch_commands(Some(Bank::Bank3), Bank::Bank3),
+            [None, None]
+        );
+        assert_eq!(
+            bank_switch_commands(None, Bank::Bank0),
+            [Some([bfc, 0b11]), None]
+        );
+        assert_eq!(
+            bank_switch_commands(None, Bank::Bank3),
+            [None, Some([bfs, 0b11])]
+        );
+    }
+
+    #[test]
+    fn receive_header_parses_rsv() {
+        let rsv = [0x40, 0x02, 0x44, 0x00, 0x80, 0x00];
+        let header = ReceiveHeader::parse(rsv, true, 0x0000..=0x0fff).unwrap();
+        assert_eq!(header.next_packet, 0x0240);
+        assert_eq!(header.len, 0x40);
+        assert!(!header.runt);
+        assert!(header.status.received_ok());
+        assert!(header.overflowed);
+
+        let runt = [0x40, 0x02, 0x03, 0x00, 0x00, 0x00];
+        let header = ReceiveHeader::parse(runt, false, 0x0000..=0x0fff).unwrap();
+        assert!(header.runt);
+        assert_eq!(header.len, 0);
+
+        // A next packet pointer in the transmit buffer cannot be followed.
+        let corrupt = [0x00, 0x10, 0x44, 0x00, 0x80, 0x00];
+        assert!(ReceiveHeader::parse(corrupt, false, 0x0000..=0x0fff).is_none());
+    }
+
+    #[test]
+    fn receive_read_pointer_is_byte_before_next_packet() {
+        assert_eq!(receive_read_pointer(0x0100, 0x0000, 0x0fff), 0x00ff);
+    }
+
+    #[test]
+    fn receive_read_pointer_wraps_to_end() {
+        assert_eq!(receive_read_pointer(0x0000, 0x0000, 0x0fff), 0x0fff);
+        assert_eq!(receive_read_pointer(0x0200, 0x0200, 0x0fff), 0x0fff);
+    }
+
+    #[test]
+    fn receive_read_pointer_is_odd() {
+        assert_eq!(receive_read_pointer(0x0101, 0x0000, 0x0fff), 0x00ff);
+    }
+}
@@ -0,0 +1,10 @@
+/// The state of a driver whose device has not been initialized yet.
+///
+/// Only register access and `initialize` are available in this state.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Uninit;
+
+/// The state of a driver whose device has been initialized, and which is ready to receive and
+/// transmit frames.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ready;
@@ -0,0 +1,550 @@
+use core::ops::{BitOr, BitOrAssign};
+
+use crate::register::PhStat2;
+
+/// The result of receiving a packet into a buffer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReceivedPacket {
+    /// Number of bytes written into the buffer.
+    pub copied: usize,
+    /// Length of the packet, excluding the CRC. Larger than `copied` if the packet was truncated.
+    pub total: usize,
+    /// The receive status flags reported by the device for the packet.
+    pub status: RxStatus,
+    /// The receive buffer overflowed since the previous packet was received, so packets may have
+    /// been dropped (EIR.RXERIF).
+    pub overflowed: bool,
+}
+
+impl ReceivedPacket {
+    /// Reports whether the buffer was too small to hold the whole packet.
+    pub const fn is_truncated(&self) -> bool {
+        self.total > self.copied
+    }
+}
+
+/// The status flags of the receive status vector, which precedes each packet in the receive
+/// buffer.
+///
+/// Unless [`ReceiveFilter::CRC_CHECK`](crate::ReceiveFilter::CRC_CHECK) is set, packets with an
+/// invalid CRC are received as well, and can only be told apart through
+/// [`RxStatus::crc_error`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RxStatus(u16);
+
+impl RxStatus {
+    /// Decodes the status word, i.e. bits 31:16 of the receive status vector.
+    pub const fn from_bits(bits: u16) -> Self {
+        RxStatus(bits)
+    }
+
+    /// The raw status word.
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    const fn bit(self, n: u32) -> bool {
+        (self.0 >> (n - 16)) & 1 != 0
+    }
+
+    /// A packet over 50,000 bit times occurred or a packet was dropped since the last receive.
+    pub const fn long_event(self) -> bool {
+        self.bit(16)
+    }
+
+    /// A carrier event was detected since the last receive.
+    pub const fn carrier_event(self) -> bool {
+        self.bit(18)
+    }
+
+    /// The CRC of the packet did not match the CRC generated internally.
+    pub const fn crc_error(self) -> bool {
+        self.bit(20)
+    }
+
+    /// The length/type field held a length that did not match the actual packet length.
+    pub const fn length_check_error(self) -> bool {
+        self.bit(21)
+    }
+
+    /// The length/type field held a length larger than 1500 bytes.
+    pub const fn length_out_of_range(self) -> bool {
+        self.bit(22)
+    }
+
+    /// The packet had a valid CRC and no symbol errors.
+    pub const fn received_ok(self) -> bool {
+        self.bit(23)
+    }
+
+    /// The destination address was a multicast address.
+    pub const fn multicast(self) -> bool {
+        self.bit(24)
+    }
+
+    /// The destination address was the broadcast address.
+    pub const fn broadcast(self) -> bool {
+        self.bit(25)
+    }
+
+    /// Additional bits were received after the end of the packet.
+    pub const fn dribble_nibble(self) -> bool {
+        self.bit(26)
+    }
+
+    /// The packet was a control frame.
+    pub const fn control_frame(self) -> bool {
+        self.bit(27)
+    }
+
+    /// The packet was a pause control frame.
+    pub const fn pause_control_frame(self) -> bool {
+        self.bit(28)
+    }
+
+    /// The packet was a control frame with an unknown opcode.
+    pub const fn unknown_opcode(self) -> bool {
+        self.bit(29)
+    }
+
+    /// The packet carried an IEEE 802.1Q VLAN tag.
+    pub const fn vlan_tagged(self) -> bool {
+        self.bit(30)
+    }
+}
+
+/// The state of a transmission started with
+/// [`Enc28j60::start_transmit`](crate::Enc28j60::start_transmit).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TxState {
+    /// The frame is still being transmitted.
+    InProgress,
+    /// The frame was transmitted.
+    Done(TxStatus),
+    /// The device aborted the transmission. The transmit status vector tells why.
+    Aborted(TxStatus),
+}
+
+/// The transmit status vector, written by the device just past ETXND after each transmission.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxStatus(u64);
+
+impl TxStatus {
+    /// Length of the transmit status vector in bytes.
+    pub const LEN: usize = 7;
+
+    /// Decodes a transmit status vector as read from buffer memory.
+    pub const fn from_bytes(bytes: [u8; Self::LEN]) -> Self {
+        let [b0, b1, b2, b3, b4, b5, b6] = bytes;
+        TxStatus(u64::from_le_bytes([b0, b1, b2, b3, b4, b5, b6, 0]))
+    }
+
+    const fn bit(self, n: u32) -> bool {
+        (self.0 >> n) & 1 != 0
+    }
+
+    /// Number of bytes in the frame, not counting collided attempts.
+    pub const fn byte_count(self) -> u16 {
+        self.0 as u16
+    }
+
+    /// Number of collisions the frame encountered during its transmission attempts.
+    pub const fn collision_count(self) -> u8 {
+        ((self.0 >> 16) & 0x0f) as u8
+    }
+
+    /// The CRC of the frame did not match the CRC generated internally.
+    pub const fn crc_error(self) -> bool {
+        self.bit(20)
+    }
+
+    /// The length/type field held a length that did not match the actual frame length.
+    pub const fn length_check_error(self) -> bool {
+        self.bit(21)
+    }
+
+    /// The length/type field held a length larger than 1500 bytes.
+    pub const fn length_out_of_range(self) -> bool {
+        self.bit(22)
+    }
+
+    /// The transmission completed successfully.
+    pub const fn done(self) -> bool {
+        self.bit(23)
+    }
+
+    /// The destination address was a multicast address.
+    pub const fn multicast(self) -> bool {
+        self.bit(24)
+    }
+
+    /// The destination address was the broadcast address.
+    pub const fn broadcast(self) -> bool {
+        self.bit(25)
+    }
+
+    /// The transmission was deferred at least once because the medium was busy.
+    pub const fn deferred(self) -> bool {
+        self.bit(26)
+    }
+
+    /// The transmission was deferred in excess of 24,287 bit times and aborted.
+    pub const fn excessive_defer(self) -> bool {
+        self.bit(27)
+    }
+
+    /// The transmission was aborted after more collisions than MACLCON1 allows.
+    pub const fn excessive_collision(self) -> bool {
+        self.bit(28)
+    }
+
+    /// A collision occurred beyond the collision window and the transmission was aborted.
+    pub const fn late_collision(self) -> bool {
+        self.bit(29)
+    }
+
+    /// The frame was longer than MAMXFL.
+    pub const fn giant(self) -> bool {
+        self.bit(30)
+    }
+
+    /// The transmission was aborted because the transmit buffer was not ready in time.
+    pub const fn underrun(self) -> bool {
+        self.bit(31)
+    }
+
+    /// Total bytes put on the wire for this frame, including collided attempts.
+    pub const fn total_bytes_on_wire(self) -> u16 {
+        (self.0 >> 32) as u16
+    }
+
+    /// The frame was a control frame.
+    pub const fn control_frame(self) -> bool {
+        self.bit(48)
+    }
+
+    /// The frame was a pause control frame.
+    pub const fn pause_control_frame(self) -> bool {
+        self.bit(49)
+    }
+
+    /// Carrier sense backpressure was applied while the frame was being transmitted.
+    pub const fn backpressure_applied(self) -> bool {
+        self.bit(50)
+    }
+
+    /// The frame carried an IEEE 802.1Q VLAN tag.
+    pub const fn vlan_tagged(self) -> bool {
+        self.bit(51)
+    }
+}
+
+/// Cumulative counts of received and transmitted frames, maintained by the driver.
+///
+/// The counters wrap around on overflow. See [`Enc28j60::stats`](crate::Enc28j60::stats).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// Frames read from the receive buffer, including those with errors.
+    pub rx_frames: u32,
+    /// Received frames whose CRC was invalid.
+    pub rx_crc_errors: u32,
+    /// Receive buffer overflows noticed while receiving or through
+    /// [`Enc28j60::rx_overflow`](crate::Enc28j60::rx_overflow). Each may have dropped several
+    /// frames.
+    pub rx_overflows: u32,
+    /// Frames transmitted successfully.
+    pub tx_frames: u32,
+    /// Aborted transmission attempts, including those which were retried.
+    pub tx_aborts: u32,
+    /// Collisions during successful transmissions.
+    pub tx_collisions: u32,
+}
+
+impl Stats {
+    /// Counts a frame read from the receive buffer with `status`.
+    pub(crate) fn count_received(&mut self, status: RxStatus) {
+        self.rx_frames = self.rx_frames.wrapping_add(1);
+        if status.crc_error() {
+            self.rx_crc_errors = self.rx_crc_errors.wrapping_add(1);
+        }
+    }
+
+    /// Counts a receive buffer overflow.
+    pub(crate) fn count_overflow(&mut self) {
+        self.rx_overflows = self.rx_overflows.wrapping_add(1);
+    }
+
+    /// Counts a successful transmission with `status`.
+    fn count_transmitted(&mut self, status: &TxStatus) {
+        self.tx_frames = self.tx_frames.wrapping_add(1);
+        let collisions = u32::from(status.collision_count());
+        self.tx_collisions = self.tx_collisions.wrapping_add(collisions);
+    }
+
+    /// Counts an aborted transmission attempt.
+    fn count_aborted(&mut self) {
+        self.tx_aborts = self.tx_aborts.wrapping_add(1);
+    }
+
+    /// Counts a transmission attempt which ended in `state`.
+    pub(crate) fn count_transmit_state(&mut self, state: &TxState) {
+        match state {
+            TxState::InProgress => {}
+            TxState::Done(status) => self.count_transmitted(status),
+            TxState::Aborted(_) => self.count_aborted(),
+        }
+    }
+}
+
+/// The state of the Ethernet link, as reported by PHSTAT2.
+///
+/// The ENC28J60 only supports 10BASE-T, so the link speed is always 10 Mbit/s.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkStatus {
+    /// The link is up (PHSTAT2.LSTAT).
+    pub up: bool,
+    /// The PHY operates in full-duplex mode (PHSTAT2.DPXSTAT).
+    ///
+    /// The ENC28J60 does not support auto-negotiation, so this reflects the configured
+    /// PHCON1.PDPXMD rather than anything negotiated with the link partner. It should match
+    /// MACON3.FULDPX; a link partner configured for the other mode results in a duplex mismatch.
+    pub full_duplex: bool,
+}
+
+impl LinkStatus {
+    /// Decodes a PHSTAT2 register value.
+    pub const fn from_phstat2(phstat2: u16) -> Self {
+        let phstat2 = PhStat2::from_bits(phstat2);
+        LinkStatus {
+            up: phstat2.contains(PhStat2::LSTAT),
+            full_duplex: phstat2.contains(PhStat2::DPXSTAT),
+        }
+    }
+}
+
+/// The silicon revision of the device, as read from EREVID.
+///
+/// Which silicon errata apply depends on the revision.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Revision {
+    /// Silicon revision B1, reported as EREVID 02h.
+    B1,
+    /// Silicon revision B4, reported as EREVID 04h.
+    B4,
+    /// Silicon revision B5, reported as EREVID 05h.
+    B5,
+    /// Silicon revision B7, reported as EREVID 06h.
+    B7,
+    /// A revision not listed in the silicon errata. A value of 00h or FFh usually means the
+    /// device is not responding.
+    Unknown(u8),
+}
+
+impl Revision {
+    /// Decodes an EREVID register value.
+    pub const fn from_erevid(erevid: u8) -> Self {
+        match erevid {
+            0x02 => Revision::B1,
+            0x04 => Revision::B4,
+            0x05 => Revision::B5,
+            0x06 => Revision::B7,
+            other => Revision::Unknown(other),
+        }
+    }
+
+    /// Returns the EREVID register value.
+    pub const fn erevid(self) -> u8 {
+        match self {
+            Revision::B1 => 0x02,
+            Revision::B4 => 0x04,
+            Revision::B5 => 0x05,
+            Revision::B7 => 0x06,
+            Revision::Unknown(erevid) => erevid,
+        }
+    }
+
+    /// Reports whether the revision is listed in the silicon errata.
+    pub const fn is_known(self) -> bool {
+        !matches!(self, Revision::Unknown(_))
+    }
+}
+
+/// The results of [`Enc28j60::self_test`](crate::Enc28j60::self_test).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Diagnostics {
+    /// The silicon revision read from EREVID.
+    pub revision: Revision,
+    /// The clock is ready (ESTAT.CLKRDY).
+    pub clock_ready: bool,
+    /// The PHY identifier, as read from PHID1 and PHID2.
+    pub phy_id: (u16, u16),
+    /// A pattern written to the buffer memory was read back intact.
+    pub memory_ok: bool,
+}
+
+impl Diagnostics {
+    /// Reports whether the PHY identifier matches the ENC28J60's: PHID1 is 0083h, and PHID2 is
+    /// 14xxh, where the low byte holds the part and revision numbers.
+    pub const fn phy_id_ok(&self) -> bool {
+        self.phy_id.0 == 0x0083 && (self.phy_id.1 & 0xff00) == 0x1400
+    }
+
+    /// Reports whether every check passed.
+    pub const fn passed(&self) -> bool {
+        self.revision.is_known() && self.clock_ready && self.phy_id_ok() && self.memory_ok
+    }
+}
+
+/// Interrupt flags, as read from EIR.
+///
+/// Flags are combined with `|`. Two of them cannot be cleared through EIR:
+///
+/// - [`InterruptFlags::PACKET`] follows EPKTCNT, and clears once every pending packet has been
+///   received.
+/// - [`InterruptFlags::LINK`] mirrors the PHY interrupt flags, and clears when PHIR is read.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterruptFlags(u8);
+
+impl InterruptFlags {
+    /// A receive error occurred, e.g. the receive buffer overflowed (RXERIF).
+    pub const RX_ERROR: InterruptFlags = InterruptFlags(1 << 0);
+    /// A transmission was aborted (TXERIF).
+    pub const TX_ERROR: InterruptFlags = InterruptFlags(1 << 1);
+    /// A transmission completed (TXIF).
+    pub const TRANSMIT: InterruptFlags = InterruptFlags(1 << 3);
+    /// The PHY reported a link change (LINKIF). Read-only.
+    pub const LINK: InterruptFlags = InterruptFlags(1 << 4);
+    /// A DMA copy or checksum calculation completed (DMAIF).
+    pub const DMA: InterruptFlags = InterruptFlags(1 << 5);
+    /// One or more packets are pending in the receive buffer (PKTIF). Read-only.
+    pub const PACKET: InterruptFlags = InterruptFlags(1 << 6);
+
+    /// The flags that can be cleared by writing to EIR.
+    const CLEARABLE: InterruptFlags =
+        InterruptFlags(Self::RX_ERROR.0 | Self::TX_ERROR.0 | Self::TRANSMIT.0 | Self::DMA.0);
+
+    /// No flags set.
+    pub const fn empty() -> Self {
+        InterruptFlags(0)
+    }
+
+    /// Creates flags from a raw EIR value.
+    pub const fn from_bits(bits: u8) -> Self {
+        InterruptFlags(bits)
+    }
+
+    /// The raw EIR value.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Reports whether no flag is set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Reports whether all flags in `other` are set.
+    pub const fn contains(self, other: InterruptFlags) -> bool {
+        (self.0 & other.0) == other.0
+    }
+
+    /// The mask to write with a bit field clear command to clear these flags. Read-only flags are
+    /// left out.
+    pub const fn clear_mask(self) -> u8 {
+        self.0 & Self::CLEARABLE.0
+    }
+}
+
+impl From<u8> for InterruptFlags {
+    fn from(bits: u8) -> Self {
+        InterruptFlags(bits)
+    }
+}
+
+impl BitOr for InterruptFlags {
+    type Output = InterruptFlags;
+
+    fn bitor(self, rhs: InterruptFlags) -> InterruptFlags {
+        InterruptFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for InterruptFlags {
+    fn bitor_assign(&mut self, rhs: InterruptFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rx_status_decodes_rsv() {
+        // Next packet at 0x0240, 64 bytes, received OK to a multicast address
+        let rsv = [0x40, 0x02, 0x40, 0x00, 0x80, 0x01];
+        let status = RxStatus::from_bits(u16::from_le_bytes([rsv[4], rsv[5]]));
+        assert!(status.received_ok());
+        assert!(status.multicast());
+        assert!(!status.broadcast());
+        assert!(!status.crc_error());
+        assert!(!status.length_check_error());
+
+        // A broadcast frame with a CRC error and a length check error
+        let status = RxStatus::from_bits(0x0230);
+        assert!(!status.received_ok());
+        assert!(status.crc_error());
+        assert!(status.length_check_error());
+        assert!(status.broadcast());
+        assert!(!status.multicast());
+        assert!(!status.length_out_of_range());
+    }
+
+    #[test]
+    fn tx_status_decodes_tsv() {
+        // 64 bytes after two collisions, done, to a multicast address after deferring, 96 bytes
+        // on the wire, VLAN tagged
+        let status = TxStatus::from_bytes([0x40, 0x00, 0x82, 0x05, 0x60, 0x00, 0x08]);
+        assert_eq!(status.byte_count(), 64);
+        assert_eq!(status.collision_count(), 2);
+        assert!(status.done());
+        assert!(status.multicast() && !status.broadcast());
+        assert!(status.deferred());
+        assert_eq!(status.total_bytes_on_wire(), 96);
+        assert!(status.vlan_tagged());
+        assert!(!status.crc_error() && !status.late_collision() && !status.underrun());
+        assert!(!status.control_frame() && !status.pause_control_frame());
+
+        // Aborted after a late collision
+        let status = TxStatus::from_bytes([0x40, 0x00, 0x01, 0x20, 0x40, 0x00, 0x00]);
+        assert!(!status.done());
+        assert!(status.late_collision());
+        assert!(!status.excessive_collision());
+    }
+
+    #[test]
+    fn revision_from_erevid() {
+        assert_eq!(Revision::from_erevid(0x02), Revision::B1);
+        assert_eq!(Revision::from_erevid(0x04), Revision::B4);
+        assert_eq!(Revision::from_erevid(0x05), Revision::B5);
+        assert_eq!(Revision::from_erevid(0x06), Revision::B7);
+        assert_eq!(Revision::from_erevid(0x00), Revision::Unknown(0x00));
+        assert_eq!(Revision::from_erevid(0xff), Revision::Unknown(0xff));
+    }
+
+    #[test]
+    fn revision_round_trips() {
+        for erevid in 0..=u8::MAX {
+            assert_eq!(Revision::from_erevid(erevid).erevid(), erevid);
+        }
+    }
+}
@@ -1,5 +1,9 @@
 #![no_std]
 
+use core::fmt;
+use core::ops::{BitOr, BitOrAssign};
+use core::str::FromStr;
+
 /// A trait that defines a minimal interface for a network driver.
 ///
 /// This trait is intended to be implemented by network drivers. Higher-level networking code
@@ -18,10 +22,120 @@ pub trait SimpleNetwork {
         ether_type: EtherType,
         data: &[u8],
     ) -> Result<(), TransmitError>;
+
+    /// Reports whether the link is up, so that higher-level code can wait for it before sending
+    /// packets.
+    ///
+    /// The default implementation returns `true`, for drivers which cannot detect the link state.
+    fn link_up(&mut self) -> Result<bool, ReceiveError> {
+        Ok(true)
+    }
+
+    /// Returns the MAC address of the network interface, for use as the source address of
+    /// transmitted packets and in ARP replies.
+    fn mac_address(&mut self) -> Result<MacAddress, ReceiveError>;
+
+    /// Returns the maximum payload length of a packet, excluding the Ethernet header and CRC.
+    ///
+    /// The default implementation returns 1500, the standard Ethernet MTU.
+    fn mtu(&self) -> usize {
+        1500
+    }
+
+    /// Configures which packets the network interface accepts.
+    ///
+    /// The default implementation returns [`ReceiveError::Unsupported`], for drivers which cannot
+    /// filter packets.
+    fn set_receive_filter(&mut self, mode: FilterMode) -> Result<(), ReceiveError> {
+        let _ = mode;
+        Err(ReceiveError::Unsupported)
+    }
+}
+
+/// An asynchronous counterpart of [`SimpleNetwork`], for drivers built on async buses.
+#[cfg(feature = "async")]
+#[allow(async_fn_in_trait)]
+pub trait AsyncSimpleNetwork {
+    /// Receive a packet from the receive buffer of the network interface.
+    /// Returns number of bytes written into `buf`.
+    async fn receive(&mut self, buf: &mut [u8]) -> Result<usize, ReceiveError>;
+
+    /// Send a packet to the transmit buffer of the network interface.
+    async fn transmit(
+        &mut self,
+        dst: &MacAddress,
+        src: &MacAddress,
+        ether_type: EtherType,
+        data: &[u8],
+    ) -> Result<(), TransmitError>;
+
+    /// Reports whether the link is up. See [`SimpleNetwork::link_up`].
+    async fn link_up(&mut self) -> Result<bool, ReceiveError> {
+        Ok(true)
+    }
+
+    /// Returns the MAC address of the network interface. See [`SimpleNetwork::mac_address`].
+    async fn mac_address(&mut self) -> Result<MacAddress, ReceiveError>;
+
+    /// Returns the maximum payload length of a packet. See [`SimpleNetwork::mtu`].
+    fn mtu(&self) -> usize {
+        1500
+    }
+
+    /// Configures which packets the network interface accepts. See
+    /// [`SimpleNetwork::set_receive_filter`].
+    async fn set_receive_filter(&mut self, mode: FilterMode) -> Result<(), ReceiveError> {
+        let _ = mode;
+        Err(ReceiveError::Unsupported)
+    }
+}
+
+/// The packets a network interface accepts, as configured with
+/// [`SimpleNetwork::set_receive_filter`].
+///
+/// Flags are combined with `|`. A packet is accepted if any of the flags accepts it, so an empty
+/// mode accepts nothing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct FilterMode(u8);
+
+impl FilterMode {
+    /// Accept packets addressed to the interface's own MAC address.
+    pub const UNICAST: FilterMode = FilterMode(1 << 0);
+    /// Accept packets addressed to the broadcast address.
+    pub const BROADCAST: FilterMode = FilterMode(1 << 1);
+    /// Accept packets addressed to any multicast address.
+    pub const MULTICAST: FilterMode = FilterMode(1 << 2);
+    /// Accept every packet, whatever its destination.
+    pub const PROMISCUOUS: FilterMode = FilterMode(1 << 3);
+
+    /// No flags set. No packet is accepted.
+    pub const fn empty() -> Self {
+        FilterMode(0)
+    }
+
+    /// Returns `true` if all flags in `other` are set.
+    pub const fn contains(self, other: FilterMode) -> bool {
+        (self.0 & other.0) == other.0
+    }
+}
+
+impl BitOr for FilterMode {
+    type Output = FilterMode;
+
+    fn bitor(self, rhs: FilterMode) -> FilterMode {
+        FilterMode(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for FilterMode {
+    fn bitor_assign(&mut self, rhs: FilterMode) {
+        self.0 |= rhs.0;
+    }
 }
 
 /// An error that can occur when receiving a packet.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ReceiveError {
     /// The user-provided buffer was too small to store the received packet.
     /// The contained `usize` is the required buffer size.
@@ -32,10 +146,13 @@ pub enum ReceiveError {
     NotInitialized,
     /// The operation timed out.
     Timeout,
+    /// The network interface does not support the operation.
+    Unsupported,
 }
 
 /// An error that can occur when transmitting a packet.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum TransmitError {
     /// The network interface aborted the transmission.
     Aborted,
@@ -55,11 +172,112 @@ pub enum TransmitError {
 pub struct MacAddress(pub [u8; 6]);
 
 impl MacAddress {
+    /// The broadcast address, `ff:ff:ff:ff:ff:ff`.
+    pub const BROADCAST: MacAddress = MacAddress([0xff; 6]);
+
     /// Returns the octets of the MAC address.
     #[must_use]
     pub const fn octets(self) -> [u8; 6] {
         self.0
     }
+
+    /// Returns `true` if this is the broadcast address.
+    #[inline]
+    pub const fn is_broadcast(self) -> bool {
+        matches!(self.0, [0xff, 0xff, 0xff, 0xff, 0xff, 0xff])
+    }
+
+    /// Returns `true` if this is a group address, i.e. the I/G bit (bit 0 of the first octet) is
+    /// set. Note that the broadcast address is also a multicast address.
+    #[inline]
+    pub const fn is_multicast(self) -> bool {
+        (self.0[0] & 0b01) != 0
+    }
+
+    /// Returns `true` if this is an individual address, i.e. the I/G bit is clear.
+    #[inline]
+    pub const fn is_unicast(self) -> bool {
+        !self.is_multicast()
+    }
+
+    /// Returns `true` if the U/L bit (bit 1 of the first octet) is set.
+    #[inline]
+    pub const fn is_locally_administered(self) -> bool {
+        (self.0[0] & 0b10) != 0
+    }
+}
+
+/// An error that can occur when parsing a MAC address from a string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseMacAddressError {
+    /// The string did not contain exactly six octets.
+    InvalidGroupCount,
+    /// An octet was not exactly two hexadecimal digits.
+    InvalidOctet,
+}
+
+impl FromStr for MacAddress {
+    type Err = ParseMacAddressError;
+
+    /// Parses six hexadecimal octets separated by either colons (`02:00:00:aa:bb:cc`) or hyphens
+    /// (`02-00-00-AA-BB-CC`). Separators may not be mixed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = if s.contains(':') { ':' } else { '-' };
+        // Count the groups first, so that input without any separator, including the empty
+        // string, is reported as such rather than as a malformed octet.
+        if s.split(separator).count() != 6 {
+            return Err(ParseMacAddressError::InvalidGroupCount);
+        }
+
+        let mut octets = [0u8; 6];
+        for (octet, group) in octets.iter_mut().zip(s.split(separator)) {
+            if group.len() != 2 || !group.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ParseMacAddressError::InvalidOctet);
+            }
+            *octet =
+                u8::from_str_radix(group, 16).map_err(|_| ParseMacAddressError::InvalidOctet)?;
+        }
+
+        Ok(MacAddress(octets))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    /// Formats the MAC address as colon-separated lowercase hex octets, e.g. `aa:bb:cc:dd:ee:ff`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl fmt::LowerHex for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl fmt::UpperHex for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02X}:{b:02X}:{c:02X}:{d:02X}:{e:02X}:{g:02X}")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for MacAddress {
+    fn format(&self, f: defmt::Formatter) {
+        let [a, b, c, d, e, g] = self.0;
+        defmt::write!(
+            f,
+            "{=u8:02x}:{=u8:02x}:{=u8:02x}:{=u8:02x}:{=u8:02x}:{=u8:02x}",
+            a,
+            b,
+            c,
+            d,
+            e,
+            g
+        );
+    }
 }
 
 impl From<[u8; 6]> for MacAddress {
@@ -108,6 +326,16 @@ impl EtherType {
     pub const VLAN: EtherType = EtherType(0x8100);
     /// IPv6 packet.
     pub const IPV6: EtherType = EtherType(0x86DD);
+    /// MPLS unicast.
+    pub const MPLS_UNICAST: EtherType = EtherType(0x8847);
+    /// PPPoE Discovery Stage (RFC 2516).
+    pub const PPPOE_DISCOVERY: EtherType = EtherType(0x8863);
+    /// PPPoE Session Stage (RFC 2516).
+    pub const PPPOE_SESSION: EtherType = EtherType(0x8864);
+    /// Service VLAN tag identifier (IEEE 802.1ad, QinQ).
+    pub const QINQ: EtherType = EtherType(0x88A8);
+    /// Link Layer Discovery Protocol (IEEE 802.1AB).
+    pub const LLDP: EtherType = EtherType(0x88CC);
 
     /// Create a new EtherType from a raw u16.
     #[inline]
@@ -121,6 +349,23 @@ impl EtherType {
         self.0
     }
 
+    /// Returns `true` if the field holds an IEEE 802.3 payload length rather than an EtherType.
+    ///
+    /// Values up to and including 1500 (`0x05DC`), the maximum 802.3 payload size, are lengths.
+    #[inline]
+    pub const fn is_length(self) -> bool {
+        self.0 <= 0x05DC
+    }
+
+    /// Returns `true` if the field holds an Ethernet II EtherType.
+    ///
+    /// Values of 1536 (`0x0600`) and above are EtherTypes. Values between 1501 and 1535 are
+    /// undefined and are neither a length nor an EtherType.
+    #[inline]
+    pub const fn is_ether_type(self) -> bool {
+        self.0 >= 0x0600
+    }
+
     /// Construct an EtherType from two network-byte-order bytes.
     #[inline]
     pub const fn from_be_bytes(bytes: [u8; 2]) -> Self {
@@ -133,3 +378,248 @@ impl EtherType {
         self.0.to_be_bytes()
     }
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for EtherType {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u16:#06x}", self.0);
+    }
+}
+
+/// The length of the frame check sequence at the end of an Ethernet frame.
+pub const FCS_LEN: usize = 4;
+
+/// Computes the frame check sequence of an Ethernet frame: the CRC-32 of IEEE 802.3 over `data`,
+/// which runs from the destination address to the end of the payload.
+///
+/// The FCS is transmitted least significant byte first, as in `ethernet_fcs(data).to_le_bytes()`.
+/// Computing it over a frame including a valid FCS yields the constant residue `0x2144df1c`.
+///
+/// ```
+/// use simple_network::ethernet_fcs;
+///
+/// assert_eq!(ethernet_fcs(b"123456789"), 0xcbf4_3926);
+/// ```
+pub const fn ethernet_fcs(data: &[u8]) -> u32 {
+    // The polynomial 04C11DB7h, bit-reversed, since octets are sent least significant bit first.
+    const POLYNOMIAL: u32 = 0xedb8_8320;
+
+    let mut crc = 0xffff_ffffu32;
+    let mut i = 0;
+    while i < data.len() {
+        crc ^= data[i] as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            let feedback = crc & 1;
+            crc >>= 1;
+            if feedback != 0 {
+                crc ^= POLYNOMIAL;
+            }
+            bit += 1;
+        }
+        i += 1;
+    }
+    !crc
+}
+
+/// Appends the frame check sequence of the frame in `buf[..len]` to it, for network interfaces
+/// which do not append one themselves. Returns the length of the frame including the FCS.
+///
+/// Fails if `buf` has no room for the [`FCS_LEN`] bytes after the frame.
+pub fn append_fcs(buf: &mut [u8], len: usize) -> Result<usize, BufferTooSmall> {
+    let fcs_end = len + FCS_LEN;
+    if buf.len() < fcs_end {
+        return Err(BufferTooSmall { needed: fcs_end });
+    }
+
+    let fcs = ethernet_fcs(&buf[..len]);
+    buf[len..fcs_end].copy_from_slice(&fcs.to_le_bytes());
+    Ok(fcs_end)
+}
+
+/// The error returned by [`append_fcs`] when the buffer has no room for the FCS.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BufferTooSmall {
+    /// The buffer length needed to hold the frame and its FCS.
+    pub needed: usize,
+}
+
+/// An error that can occur when parsing or serializing a frame header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    /// The buffer is shorter than the header.
+    BufferTooSmall,
+}
+
+/// The header of an Ethernet II frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct EthernetHeader {
+    /// Destination MAC address.
+    pub dst: MacAddress,
+    /// Source MAC address.
+    pub src: MacAddress,
+    /// EtherType, or the payload length for IEEE 802.3 frames.
+    pub ether_type: EtherType,
+}
+
+impl EthernetHeader {
+    /// Length of the header in bytes.
+    pub const LEN: usize = 14;
+
+    /// Parse the header at the start of `buf`, returning it along with the remaining payload.
+    pub fn parse(buf: &[u8]) -> Result<(EthernetHeader, &[u8]), ParseError> {
+        let (header, payload) = buf
+            .split_first_chunk::<{ Self::LEN }>()
+            .ok_or(ParseError::BufferTooSmall)?;
+
+        let mut dst = [0u8; 6];
+        let mut src = [0u8; 6];
+        dst.copy_from_slice(&header[0..6]);
+        src.copy_from_slice(&header[6..12]);
+
+        let header = EthernetHeader {
+            dst: MacAddress(dst),
+            src: MacAddress(src),
+            ether_type: EtherType::from_be_bytes([header[12], header[13]]),
+        };
+        Ok((header, payload))
+    }
+
+    /// Serialize the header into the start of `buf` in network byte order.
+    /// Returns the number of bytes written, which is always [`EthernetHeader::LEN`].
+    pub fn write_into(&self, buf: &mut [u8]) -> Result<usize, ParseError> {
+        let (header, _) = buf
+            .split_first_chunk_mut::<{ Self::LEN }>()
+            .ok_or(ParseError::BufferTooSmall)?;
+
+        header[0..6].copy_from_slice(&self.dst.0);
+        header[6..12].copy_from_slice(&self.src.0);
+        header[12..14].copy_from_slice(&self.ether_type.to_be_bytes());
+        Ok(Self::LEN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ethernet_fcs_matches_check_values() {
+        assert_eq!(ethernet_fcs(b""), 0);
+        assert_eq!(ethernet_fcs(b"a"), 0xe8b7_be43);
+        assert_eq!(ethernet_fcs(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            ethernet_fcs(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+    }
+
+    #[test]
+    fn mac_address_from_str() {
+        let mac = MacAddress::from([0x02, 0x00, 0x5e, 0xaa, 0xbb, 0xcc]);
+        assert_eq!("02:00:5e:aa:bb:cc".parse(), Ok(mac));
+        assert_eq!("02:00:5E:AA:BB:CC".parse(), Ok(mac));
+        assert_eq!("02-00-5e-Aa-bB-cc".parse(), Ok(mac));
+
+        for (input, error) in [
+            ("", ParseMacAddressError::InvalidGroupCount),
+            ("02005eaabbcc", ParseMacAddressError::InvalidGroupCount),
+            ("02:00:5e:aa:bb", ParseMacAddressError::InvalidGroupCount),
+            (
+                "02:00:5e:aa:bb:cc:dd",
+                ParseMacAddressError::InvalidGroupCount,
+            ),
+            ("02:00-5e:aa:bb:cc", ParseMacAddressError::InvalidGroupCount),
+            ("02:00:5e:aa:bb:", ParseMacAddressError::InvalidOctet),
+            ("02:00:5e:aa:bb:c", ParseMacAddressError::InvalidOctet),
+            ("02:00:5e:aa:bb:ccc", ParseMacAddressError::InvalidOctet),
+            ("02:00:5e:aa:bb:cg", ParseMacAddressError::InvalidOctet),
+            ("02:00:5e:aa:bb:+c", ParseMacAddressError::InvalidOctet),
+        ] {
+            assert_eq!(input.parse::<MacAddress>(), Err(error), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn ethernet_header_round_trips() {
+        let header = EthernetHeader {
+            dst: MacAddress::BROADCAST,
+            src: MacAddress::from([0x02, 0, 0, 0xaa, 0xbb, 0xcc]),
+            ether_type: EtherType::IPV6,
+        };
+        let mut buf = [0u8; 20];
+        assert_eq!(header.write_into(&mut buf), Ok(EthernetHeader::LEN));
+        assert_eq!(buf[12..14], [0x86, 0xdd]);
+        assert_eq!(EthernetHeader::parse(&buf), Ok((header, &[0u8; 6][..])));
+
+        let mut short = [0u8; EthernetHeader::LEN - 1];
+        assert_eq!(
+            header.write_into(&mut short),
+            Err(ParseError::BufferTooSmall)
+        );
+        assert_eq!(short, [0; EthernetHeader::LEN - 1]);
+    }
+
+    #[test]
+    fn ethernet_header_parse_rejects_short_buffers() {
+        let frame: [u8; 14] = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02, 0, 0, 0xaa, 0xbb, 0xcc, 0x08, 0x06,
+        ];
+        assert_eq!(EthernetHeader::parse(&[]), Err(ParseError::BufferTooSmall));
+        assert_eq!(
+            EthernetHeader::parse(&frame[..13]),
+            Err(ParseError::BufferTooSmall)
+        );
+
+        let (header, payload) = EthernetHeader::parse(&frame).unwrap();
+        assert_eq!(header.dst, MacAddress::BROADCAST);
+        assert_eq!(header.ether_type, EtherType::ARP);
+        assert!(payload.is_empty());
+    }
+
+    #[test]
+    fn mac_address_classification() {
+        let broadcast = MacAddress::BROADCAST;
+        assert!(broadcast.is_broadcast() && broadcast.is_multicast());
+        assert!(broadcast.is_locally_administered());
+
+        let multicast = MacAddress::from([0x01, 0x00, 0x5e, 0x00, 0x00, 0x01]);
+        assert!(!multicast.is_broadcast() && multicast.is_multicast());
+        assert!(!multicast.is_locally_administered());
+
+        let local = MacAddress::from([0x02, 0, 0, 0xaa, 0xbb, 0xcc]);
+        assert!(!local.is_broadcast() && local.is_unicast());
+        assert!(local.is_locally_administered());
+
+        // The all-zero address is a universally administered unicast address.
+        let zero = MacAddress::from([0; 6]);
+        assert!(!zero.is_broadcast() && zero.is_unicast());
+        assert!(!zero.is_locally_administered());
+    }
+
+    #[test]
+    fn ether_type_length_boundaries() {
+        assert!(EtherType::new(0).is_length());
+        assert!(EtherType::new(1500).is_length());
+        assert!(!EtherType::new(1500).is_ether_type());
+        // 1501 to 1535 are neither a length nor an EtherType.
+        assert!(!EtherType::new(1501).is_length());
+        assert!(!EtherType::new(1501).is_ether_type());
+        assert!(!EtherType::new(0x05ff).is_ether_type());
+        assert!(EtherType::new(0x0600).is_ether_type());
+        assert!(!EtherType::new(0x0600).is_length());
+        assert!(EtherType::IPV4.is_ether_type());
+    }
+
+    #[test]
+    fn append_fcs_leaves_residue() {
+        let mut buf = [0u8; 64];
+        buf[..6].fill(0xff);
+        buf[6..12].copy_from_slice(&[0x02, 0, 0, 0xaa, 0xbb, 0xcc]);
+        buf[12..14].copy_from_slice(&EtherType::ARP.to_be_bytes());
+
+        assert_eq!(append_fcs(&mut buf, 60), Ok(64));
+        assert_eq!(ethernet_fcs(&buf), 0x2144_df1c);
+        assert_eq!(append_fcs(&mut buf, 61), Err(BufferTooSmall { needed: 65 }));
+    }
+}
@@ -0,0 +1,88 @@
+//! PHY ループバックで送信したフレームを受信し直すスモークテスト。ケーブルは不要。
+
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use defmt_rtt as _;
+use embedded_hal_bus::spi::ExclusiveDevice;
+use hal::prelude::*;
+use panic_probe as _;
+use stm32f4xx_hal::{self as hal, hal_02::spi::MODE_0, rcc::Config, spi::Spi};
+
+use enc28j60::{DEFAULT_MAC_ADDRESS, Duplex, Enc28j60};
+
+#[entry]
+fn main() -> ! {
+    let cp = cortex_m::peripheral::Peripherals::take().unwrap();
+    let dp = hal::pac::Peripherals::take().unwrap();
+
+    // システムクロックの設定
+    let mut rcc = dp.RCC.freeze(Config::hsi().sysclk(16.MHz()));
+
+    // GPIO 初期化
+    let gpioa = dp.GPIOA.split(&mut rcc);
+    let gpiob = dp.GPIOB.split(&mut rcc);
+
+    // SPI1 ピン設定
+    let sck = gpioa.pa5.into_alternate();
+    let miso = gpioa.pa6.into_alternate();
+    let mosi = gpioa.pa7.into_alternate();
+
+    // ENC28J60 制御ピン
+    let mut cs = gpiob.pb1.into_push_pull_output();
+    let reset = gpiob.pb0.into_push_pull_output();
+    let int = gpioa.pa1.into_pull_up_input();
+
+    let mut spi = Spi::new(
+        dp.SPI1,
+        (Some(sck), Some(miso), Some(mosi)),
+        MODE_0,
+        8.MHz(),
+        &mut rcc,
+    );
+
+    let dly = cp.SYST.delay(&rcc.clocks);
+    let dev = ExclusiveDevice::new(&mut spi, &mut cs, dly).expect("Set up SpiDevice");
+    let mut enc = Enc28j60::new(dev, int, reset);
+
+    let mut dly = dp.TIM2.delay_us(&mut rcc);
+    enc.reset(&mut dly).expect("reset");
+
+    // ループバックは全二重でしか動作しない
+    let mut enc = enc
+        .initialize(&mut dly, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+        .expect("initialize");
+    enc.enable_loopback().expect("enable_loopback");
+
+    // 初期化直後の受信フィルタはすべてのフレームを受け付ける
+    let payload = *b"enc28j60 loopback smoke test, padded to the minimum frame size";
+    enc.transmit(&[0xff; 6], &DEFAULT_MAC_ADDRESS, 0x88b5, &payload)
+        .expect("transmit");
+
+    let mut buf = [0u8; 1518];
+    let mut received = None;
+    for _ in 0..1000 {
+        received = enc.receive(&mut buf).expect("receive");
+        if received.is_some() {
+            break;
+        }
+        cortex_m::asm::delay(16_000);
+    }
+
+    match received {
+        Some(packet) if packet.copied >= 14 && buf[14..packet.copied].starts_with(&payload) => {
+            defmt::info!("Loopback OK: {} bytes", packet.total);
+        }
+        Some(packet) => {
+            defmt::error!("Loopback mismatch: {=[u8]:x}", &buf[..packet.copied]);
+        }
+        None => defmt::error!("Loopback timed out"),
+    }
+
+    enc.disable_loopback().expect("disable_loopback");
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
@@ -6,10 +6,11 @@ use defmt_rtt as _;
 use embedded_hal_bus::spi::ExclusiveDevice;
 use hal::prelude::*;
 use panic_probe as _;
-use simple_network::SimpleNetwork;
+use simple_network::{EthernetHeader, MacAddress, SimpleNetwork};
 use stm32f4xx_hal::{self as hal, hal_02::spi::MODE_0, rcc::Config, spi::Spi};
 
-use enc28j60::{Enc28j60, register};
+use enc28j60::register::{self, Estat};
+use enc28j60::{DEFAULT_MAC_ADDRESS, Duplex, Enc28j60};
 
 #[entry]
 fn main() -> ! {
@@ -63,18 +64,28 @@ fn main() -> ! {
     let dev = ExclusiveDevice::new(&mut spi, &mut cs, dly).expect("Set up SpiDevice");
     let mut enc = Enc28j60::new(dev, int, reset);
 
-    let estat_val = enc.read_control(register::ESTAT).unwrap_or(0xFF);
-    defmt::info!("ESTAT={:?}", estat_val);
+    let estat = enc.read_reg::<Estat>(register::ESTAT).unwrap_or_default();
+    defmt::info!("ESTAT={}", estat);
 
     let mut dly = dp.TIM2.delay_us(&mut rcc);
-    enc.initialize(&mut dly).expect("initialize");
-    let estat_val = enc.read_control(register::ESTAT).unwrap_or(0xFF);
-    defmt::info!("ESTAT={:?}", estat_val);
+    let mut enc = enc
+        .initialize(&mut dly, &DEFAULT_MAC_ADDRESS, Duplex::Full)
+        .expect("initialize");
+    let mac = MacAddress::from(enc.read_mac_address().unwrap_or_default());
+    defmt::info!("MAC={}", mac);
+
+    // A locally administered address can be switched in at runtime.
+    enc.set_mac_address(&[0x02, 0x00, 0x00, 0xaa, 0xbb, 0xcc])
+        .expect("set_mac_address");
+    let mac = MacAddress::from(enc.read_mac_address().unwrap_or_default());
+    defmt::info!("MAC={}", mac);
+    let estat = enc.read_reg::<Estat>(register::ESTAT).unwrap_or_default();
+    defmt::info!("ESTAT={}", estat);
 
     let mut buf = [0u8; 1518];
     loop {
         cortex_m::asm::delay(1_000_000);
-        match enc.read_control(register::EPKTCNT) {
+        match enc.pending_packets() {
             Ok(count) => {
                 if orange_led.is_set_high() {
                     orange_led.set_low();
@@ -95,24 +106,24 @@ fn analyze_ether_frame(snp: &mut impl SimpleNetwork, buf: &mut [u8]) {
         Ok(n) => {
             defmt::info!("Received {} bytes", n);
 
-            let dst = &buf[0..6];
-            let src = &buf[6..12];
-            let typ = &buf[12..14];
-            // let dat = &buf[14..n];
+            let Ok((header, payload)) = EthernetHeader::parse(&buf[..n]) else {
+                defmt::warn!("Runt frame");
+                return;
+            };
             defmt::debug!(
                 r#"Frame layout of packet:
-     Source MAC: {:#x}
-Destination MAC: {:#x}
-     Ether Type: {:#x}
+     Source MAC: {}
+Destination MAC: {}
+     Ether Type: {}
     Data Length: {} bytes"#,
-                src,
-                dst,
-                typ,
-                n - 14
+                header.src,
+                header.dst,
+                header.ether_type,
+                payload.len()
             );
         }
-        Err(_) => {
-            defmt::error!("Error receiving packet");
+        Err(e) => {
+            defmt::error!("Error receiving packet: {}", e);
         }
     }
 }